@@ -1,34 +1,50 @@
-use std::fmt;
+use std::fmt::{self, Write};
 use chrono::prelude::*;
 use datalist::DataList;
 use datamap::DataMap;
+use serializers::Output;
 
 pub mod parsers;
+pub mod serializers;
+pub mod borrowed;
 pub mod datamap;
 pub mod datalist;
+pub mod orderedmap;
+pub mod dataerror;
+pub mod diagnostic;
+pub mod spans;
 
-pub struct DataError {
-    pub message: String
-}
-
-impl fmt::Debug for DataError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
-    }
-}
+pub use borrowed::BorrowedValue;
+pub use dataerror::{DataError, DataErrorKind, DataPath, Position};
+pub use diagnostic::RenderStyle;
+pub use serializers::SerializeOptions;
+pub use spans::{Span, Spans};
 
-trait SerializableData {
+/// Text form of a value, map or list. See the `serializers` module for the entry points.
+pub trait SerializableData {
     fn get_serialized_string(&self) -> String {
         let mut s = String::new();
-        self.serialize_to_string(&mut s, 0);
+        self.serialize_to_string(&mut s, 0, &SerializeOptions::default());
         return s;
     }
 
-    fn serialize_to_string(&self, buffer: &mut String, indent: u8);
+    fn serialize_to_string(&self, buffer: &mut String, indent: usize, options: &SerializeOptions) {
+        // Writing to a `String` cannot fail.
+        let _ = self.serialize_to(&mut Output::new(buffer), indent, options);
+    }
+
+    fn serialize_to(&self, out: &mut Output, indent: usize, options: &SerializeOptions) -> fmt::Result;
+
+    /// Appends the canonical form to `buffer`. See `serializers::serialize_canonical`.
+    fn serialize_canonical_to(&self, buffer: &mut String, path: &mut DataPath) -> Result<(), DataError>;
 }
 
+/// Numeric value. Integers too large for `Int` are held as `UInt` when they fit a `u64` and as
+/// `Float` beyond that.
+#[derive(Debug, PartialEq)]
 pub enum Number {
     Int(i64),
+    UInt(u64),
     Float(f64),
     PositiveInfinity,
     NegativeInfinity,
@@ -39,7 +55,8 @@ impl fmt::Display for Number {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Number::Int(i) => write!(f, "{}", i),
-            Number::Float(fl) => write!(f, "{}", fl),
+            Number::UInt(u) => write!(f, "{}", u),
+            Number::Float(fl) => write_float(f, *fl),
             Number::PositiveInfinity => write!(f, "Infinity"),
             Number::NegativeInfinity => write!(f, "-Infinity"),
             Number::NaN => write!(f, "NaN")
@@ -47,20 +64,38 @@ impl fmt::Display for Number {
     }
 }
 
+/// Writes the fewest digits that parse back to the same `f64`, always keeping a fraction or
+/// exponent so that the value is read back as a float rather than an integer. Exponent form is
+/// only used for very large and very small magnitudes, as `{:?}` does.
+fn write_float(f: &mut fmt::Formatter<'_>, fl: f64) -> fmt::Result {
+    if fl.is_nan() {
+        write!(f, "NaN")
+    } else if fl.is_infinite() {
+        write!(f, "{}", if fl > 0.0 { "Infinity" } else { "-Infinity" })
+    } else {
+        write!(f, "{:?}", fl)
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, PartialEq)]
 pub enum Date {
     DateTimeUtc(DateTime<Utc>),
-    DateTimeOffset(DateTime<FixedOffset>)
+    DateTimeOffset(DateTime<FixedOffset>),
+    NaiveDate(NaiveDate)
 }
 
 impl fmt::Display for Date {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Date::DateTimeUtc(dt) => write!(f, "{}", dt.to_rfc3339_opts(SecondsFormat::Millis, true)),
-            Date::DateTimeOffset(dt) => write!(f, "{}", dt.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            Date::DateTimeUtc(dt) => write!(f, "{}", dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            Date::DateTimeOffset(dt) => write!(f, "{}", dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            Date::NaiveDate(d) => write!(f, "{}", d.format("%Y-%m-%d")),
         }
     }
 }
 
+#[derive(Debug, PartialEq)]
 pub enum DataValue {
     String(String),
     Number(Number),
@@ -92,15 +127,31 @@ impl fmt::Display for DataValue {
 }
 
 impl SerializableData for DataValue {
-    fn serialize_to_string(&self, buffer: &mut String, indent: u8) {
+    fn serialize_to(&self, out: &mut Output, indent: usize, options: &SerializeOptions) -> fmt::Result {
         match self {
-            DataValue::String(s) => buffer.push_str(&format!("\"{}\"", s)),
-            DataValue::Date(d) => buffer.push_str(&format!("\"{}\"", d)),
-            DataValue::DataMap(map) => map.serialize_to_string(buffer, indent),
-            DataValue::DataList(list) => list.serialize_to_string(buffer, indent),
-            _ => return buffer.push_str(&self.get_string()),            
+            DataValue::String(s) => serializers::write_string(out, s, options),
+            DataValue::Date(d) => write!(out, "\"{}\"", d),
+            DataValue::DataMap(map) => map.serialize_to(out, indent, options),
+            DataValue::DataList(list) => list.serialize_to(out, indent, options),
+            _ => return out.write_str(&self.get_string()),            
         }    
      }
+
+    fn serialize_canonical_to(&self, buffer: &mut String, path: &mut DataPath) -> Result<(), DataError> {
+        return serializers::canonical::write_value(buffer, self, path);
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::parsers::parse_number;
+    use super::Number;
 
+    #[test]
+    fn floats_display_in_plain_form_with_fewest_digits() {
+        for (f, text) in [(10.0, "10.0"), (1500.0, "1500.0"), (100.0, "100.0"), (0.001, "0.001"), (0.1, "0.1"), (-2.5, "-2.5"), (1e16, "1e16"), (1.5e300, "1.5e300"), (1e-7, "1e-7")] {
+            assert_eq!(Number::Float(f).to_string(), text);
+            assert_eq!(parse_number(text).unwrap(), Number::Float(f));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::borrow::Cow;
+
+use super::datalist::DataList;
+use super::datamap::DataMap;
+use super::orderedmap::OrderedMap;
+use super::{DataValue, Date, Number};
+
+/// Parsed value that borrows its strings and keys from the input instead of copying them. Only
+/// strings containing escape sequences are allocated. Produced by `parsers::parse_borrowed`, and
+/// converted with `into_owned` when it has to outlive the input.
+pub enum BorrowedValue<'a> {
+    String(Cow<'a, str>),
+    Number(Number),
+    Bool(bool),
+    Date(Date),
+    Map(OrderedMap<Cow<'a, str>, BorrowedValue<'a>>),
+    List(Vec<BorrowedValue<'a>>),
+    Null
+}
+
+#[allow(dead_code)]
+impl<'a> BorrowedValue<'a> {
+    /// Value under `key` when this is a map holding it.
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Map(map) => return map.get(key),
+            _ => return None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedValue::String(s) => return Some(s),
+            _ => return None
+        }
+    }
+
+    pub fn into_owned(self) -> DataValue {
+        match self {
+            BorrowedValue::String(s) => return DataValue::String(s.into_owned()),
+            BorrowedValue::Number(n) => return DataValue::Number(n),
+            BorrowedValue::Bool(b) => return DataValue::Bool(b),
+            BorrowedValue::Date(d) => return DataValue::Date(d),
+            BorrowedValue::Map(map) => {
+                let mut owned = DataMap::new();
+                for (k, v) in map {
+                    owned.put(&k, v.into_owned());
+                }
+                return DataValue::DataMap(owned);
+            },
+            BorrowedValue::List(list) => {
+                let mut owned = DataList::new();
+                for v in list {
+                    owned.push(v.into_owned());
+                }
+                return DataValue::DataList(owned);
+            },
+            BorrowedValue::Null => return DataValue::Null
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+use super::diagnostic::{self, RenderStyle};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataErrorKind {
+    Io,
+    InvalidUtf8,
+    InvalidUtf16,
+    InvalidUtf32,
+    UnexpectedEnd,
+    UnexpectedCharacter,
+    ExpectedMap,
+    QuoteInKey,
+    ExpectedColon,
+    ExpectedMapSeparator,
+    ExpectedListSeparator,
+    InvalidEscape,
+    LoneSurrogate,
+    UnquotedKey,
+    UnquotedString,
+    ControlCharacter,
+    InvalidNumber,
+    NonFiniteNumber,
+    NumberOutOfRange,
+    InvalidDate,
+    DateOutOfRange,
+    DepthLimitExceeded,
+    StringTooLong,
+    TooManyEntries,
+    InputTooLarge,
+    DuplicateKey,
+    TrailingContent
+}
+
+/// Location in the input. `offset` is in bytes from the start of the input, `line` and `column`
+/// are 1-based, with the column counted in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Position {
+    pub fn start() -> Self {
+        Self { offset: 0, line: 1, column: 1 }
+    }
+
+    /// Translates a position within a single line token that starts at `self` into a position in
+    /// the enclosing input.
+    pub fn advance(&self, within: Position) -> Position {
+        Position { offset: self.offset + within.offset, line: self.line, column: self.column + within.column - 1 }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize)
+}
+
+/// Key and index path from the document root to a value, displayed as `orders[3].amount`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DataPath {
+    pub segments: Vec<PathSegment>
+}
+
+#[allow(dead_code)]
+impl DataPath {
+    pub fn new() -> Self {
+        Self { segments: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn push_key(&mut self, key: &str) {
+        self.segments.push(PathSegment::Key(key.to_string()));
+    }
+
+    pub fn push_index(&mut self, index: usize) {
+        self.segments.push(PathSegment::Index(index));
+    }
+}
+
+impl From<&str> for DataPath {
+    /// Reads a path in its displayed form, e.g. `orders[3].amount`. Keys containing `.` or `[`
+    /// cannot be written this way and need `push_key` instead.
+    fn from(s: &str) -> Self {
+        let mut path = DataPath::new();
+        for part in s.split('.') {
+            let (key, indexes) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if !key.is_empty() {
+                path.push_key(key);
+            }
+            for index in indexes.split('[').filter_map(|i| i.strip_suffix(']')) {
+                match index.parse() {
+                    Ok(i) => path.push_index(i),
+                    Err(_) => path.push_key(index)
+                }
+            }
+        }
+        return path;
+    }
+}
+
+impl fmt::Display for DataPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for segment in self.segments.iter() {
+            match segment {
+                PathSegment::Key(k) => {
+                    if !first {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{}", k)?;
+                },
+                PathSegment::Index(i) => write!(f, "[{}]", i)?
+            }
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+pub struct DataError {
+    pub kind: DataErrorKind,
+    pub message: String,
+    pub position: Position,
+    pub path: DataPath
+}
+
+impl DataError {
+    pub fn new(kind: DataErrorKind, message: &str, position: Position) -> Self {
+        Self { kind, message: message.to_string(), position, path: DataPath::new() }
+    }
+
+    pub fn with_path(mut self, path: DataPath) -> Self {
+        if self.path.is_empty() {
+            self.path = path;
+        }
+        self
+    }
+
+    /// Renders the error with an excerpt of `source`, the input that was parsed. See `diagnostic::render`.
+    pub fn render(&self, source: &str, style: RenderStyle) -> String {
+        diagnostic::render(self, source, style)
+    }
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.position)?;
+        if !self.path.is_empty() {
+            write!(f, " ({})", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::error::Error for DataError {}
@@ -1,7 +1,8 @@
 use std::fmt;
 
-use super::{DataValue, SerializableData};
+use super::{serializers::{self, Output}, DataError, DataPath, DataValue, SerializableData, SerializeOptions};
 
+#[derive(Debug, Default, PartialEq)]
 pub struct DataList {
     vec: std::vec::Vec<DataValue>
 }
@@ -19,6 +20,18 @@ impl DataList {
     pub fn push_string(&mut self, val: &str) {
         self.vec.push(DataValue::String(val.to_string()));
     } 
+
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, DataValue> {
+        self.vec.iter()
+    }
 }
 
 impl fmt::Display for DataList {
@@ -28,24 +41,11 @@ impl fmt::Display for DataList {
 }
 
 impl SerializableData for DataList {
-    fn serialize_to_string(&self, buffer: &mut String, indent: u8) {
-        let mut indent_str = String::new();
-        for _ in 0..indent {
-            indent_str.push_str("  ");
-        }
-        buffer.push_str("[\r\n");
-        let mut i = self.vec.len();
-        for val in self.vec.iter() {
-            buffer.push_str(&indent_str);
-            buffer.push_str("  ");
-            val.serialize_to_string(buffer, indent + 1);
-            if i > 1 {
-                buffer.push_str(",");
-            }
-            buffer.push_str("\r\n");
-            i -= 1;
-        }
-        buffer.push_str(&indent_str);
-        buffer.push_str("]");
+    fn serialize_to(&self, out: &mut Output, indent: usize, options: &SerializeOptions) -> fmt::Result {
+        return serializers::write_list(out, self, indent, 0, options);
+    }
+
+    fn serialize_canonical_to(&self, buffer: &mut String, path: &mut DataPath) -> Result<(), DataError> {
+        return serializers::canonical::write_list(buffer, self, path);
     }
-}
\ No newline at end of file
+}
@@ -1,19 +1,35 @@
 
-use std::collections::HashMap;
 use std::fmt;
 
 use chrono::{DateTime, Utc};
 
-use super::{datalist::DataList, DataValue, Date, Number, SerializableData};
+use super::{datalist::DataList, orderedmap::OrderedMap, serializers::{self, Output}, DataError, DataPath, DataValue, Date, Number, SerializableData, SerializeOptions};
 
+/// Map from keys to values that keeps its keys in the order they were first put, or in key order
+/// when sorted, so that parsing and serializing preserve the order of a document.
+#[derive(Debug, Default, PartialEq)]
 pub struct DataMap {
-    map: std::collections::HashMap<String, DataValue>
+    map: OrderedMap<String, DataValue>
 }
 
 #[allow(dead_code)]
 impl DataMap {
     pub fn new() -> Self {
-        Self { map: HashMap::new() }
+        Self { map: OrderedMap::new() }
+    }
+
+    /// An empty map that keeps its keys in sorted order rather than insertion order.
+    pub fn sorted() -> Self {
+        Self { map: OrderedMap::sorted() }
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        self.map.is_sorted()
+    }
+
+    /// Puts the keys of this map, but not of maps held in it, in sorted order and keeps them so.
+    pub fn sort(&mut self) {
+        self.map.sort();
     }
 
     pub fn new_with(arr: &[&str]) -> Self {
@@ -36,6 +52,26 @@ impl DataMap {
         }
     }
 
+    pub fn get_mut(&mut self, k: &str) -> Option<&mut DataValue> {
+        self.map.get_mut(k)
+    }
+
+    pub fn has(&self, k: &str) -> bool {
+        self.map.contains_key(k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&String, &DataValue)> + ExactSizeIterator + Clone {
+        self.map.iter()
+    }
+
     pub fn get_string(&self, k: &str) -> String {
         let val = self.get(k);
         return val.get_string();
@@ -45,6 +81,11 @@ impl DataMap {
         self.map.insert(k.to_string(), v);
     }
 
+    /// Like `put`, taking ownership of the key rather than copying it.
+    pub fn put_owned(&mut self, k: String, v: DataValue) {
+        self.map.insert(k, v);
+    }
+
     pub fn put_string(&mut self, k: &str, v: &str) {
         self.put(k, DataValue::String(v.to_string()));
     }
@@ -81,26 +122,75 @@ impl fmt::Display for DataMap {
 }
 
 impl SerializableData for DataMap {
-    fn serialize_to_string(&self, buffer: &mut String, indent: u8) {
-        let mut indent_str = String::new();
-        for _ in 0..indent {
-            indent_str.push_str("  ");
-        }
-        buffer.push_str("{\r\n");
-        let mut i = self.map.len();
-        for (key, val) in self.map.iter() {
-            buffer.push_str(&indent_str);
-            buffer.push_str("  \"");
-            buffer.push_str(key);
-            buffer.push_str("\":");
-            val.serialize_to_string(buffer, indent + 1);
-            if i > 1 {
-                buffer.push_str(",");
-            }
-            buffer.push_str("\r\n");
-            i -= 1;
+    fn serialize_to(&self, out: &mut Output, indent: usize, options: &SerializeOptions) -> fmt::Result {
+        return serializers::write_map(out, self, indent, 0, options);
+    }
+
+    fn serialize_canonical_to(&self, buffer: &mut String, path: &mut DataPath) -> Result<(), DataError> {
+        return serializers::canonical::write_map(buffer, self, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parsers::{parse, parse_map};
+    use super::super::serializers::{serialize_with, SerializeOptions};
+    use super::super::DataValue;
+    use super::DataMap;
+
+    /// A document whose keys are out of key order, with more of them than a map holds unindexed.
+    fn document(n: usize) -> String {
+        let entries: Vec<String> = (0..n).map(|i| format!("\"k{:02}\":{}", (i * 7) % n, i)).collect();
+        return format!("{{\"z\":{{\"b\":1,\"a\":[{{\"y\":1,\"x\":2}}]}},{}}}", entries.join(","));
+    }
+
+    fn keys(map: &DataMap) -> Vec<String> {
+        return map.iter().map(|(k, _)| k.clone()).collect();
+    }
+
+    #[test]
+    fn parse_and_serialize_keep_the_order_of_the_document() {
+        for n in [3, 40] {
+            let input = document(n);
+            let map = parse_map(&input).unwrap();
+            let expected: Vec<String> = ["z".to_string()].into_iter().chain((0..n).map(|i| format!("k{:02}", (i * 7) % n))).collect();
+            assert_eq!(keys(&map), expected);
+            assert_eq!(serialize_with(&map, &SerializeOptions::compact()), input);
+            let reparsed = parse(&map.to_string()).unwrap();
+            assert_eq!(serialize_with(&reparsed, &SerializeOptions::compact()), input);
         }
-        buffer.push_str(&indent_str);
-        buffer.push_str("}");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn sorted_maps_and_sort_keys_write_keys_in_order() {
+        let mut map = DataMap::sorted();
+        map.put_int("b", 1);
+        map.put_int("c", 2);
+        map.put_int("a", 3);
+        map.put_int("b", 4);
+        assert_eq!(keys(&map), ["a", "b", "c"]);
+        assert_eq!(serialize_with(&map, &SerializeOptions::compact()), "{\"a\":3,\"b\":4,\"c\":2}");
+
+        let input = document(40);
+        let mut map = parse_map(&input).unwrap();
+        let sort_keys = SerializeOptions { sort_keys: true, ..SerializeOptions::compact() };
+        let sorted = serialize_with(&map, &sort_keys);
+        assert!(sorted.starts_with("{\"k00\":0,\"k01\":"), "{}", sorted);
+        assert!(sorted.ends_with(",\"z\":{\"a\":[{\"x\":2,\"y\":1}],\"b\":1}}"), "{}", sorted);
+        // Sorting the map itself leaves the maps held in it alone.
+        map.sort();
+        assert_eq!(keys(&map).first().map(|k| k.as_str()), Some("k00"));
+        assert!(serialize_with(&map, &SerializeOptions::compact()).ends_with(",\"z\":{\"b\":1,\"a\":[{\"y\":1,\"x\":2}]}}"));
+        assert_eq!(DataValue::DataMap(map), parse(&input).unwrap());
+    }
+
+    #[test]
+    fn replacing_a_value_keeps_the_key_in_place() {
+        let mut map = parse_map(&document(40)).unwrap();
+        map.put_string("k07", "new");
+        map.put_int("z", 0);
+        assert_eq!(keys(&map)[..3], ["z", "k00", "k07"]);
+        assert_eq!(map.get_string("k07"), "new");
+        assert_eq!(map.len(), 41);
+    }
+}
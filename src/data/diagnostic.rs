@@ -0,0 +1,91 @@
+use super::{DataError, DataErrorKind};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Output flavour of `render`: plain text, or text coloured with ANSI escape codes for terminals.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RenderStyle {
+    Plain,
+    Ansi
+}
+
+/// Renders `error` against the input it came from, quoting the offending line with a caret under
+/// the error position and a hint on how to fix it:
+///
+/// ```text
+/// error: Unexpected '"' in unquoted key
+///  --> line 2, column 5 (server)
+///   |
+/// 2 |   ho"st: 1
+///   |     ^
+///   = hint: unquoted key containing '"', quote the whole key and escape the quote as \"
+/// ```
+pub fn render(error: &DataError, source: &str, style: RenderStyle) -> String {
+    let paint = |code: &'static str| if style == RenderStyle::Ansi { code } else { "" };
+    let (red, blue, cyan, bold, reset) = (paint(RED), paint(BLUE), paint(CYAN), paint(BOLD), paint(RESET));
+
+    let mut offset = error.position.offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[offset..].find('\n').map_or(source.len(), |i| offset + i);
+    let line = source[line_start..line_end].trim_end_matches('\r').replace('\t', " ");
+    let caret = source[line_start..offset].chars().count();
+    let number = error.position.line.to_string();
+    let gutter = " ".repeat(number.len());
+
+    let mut s = String::new();
+    s.push_str(&format!("{}error{}{}: {}{}\n", red, reset, bold, error.message, reset));
+    s.push_str(&format!("{}{}-->{} {}", gutter, blue, reset, error.position));
+    if !error.path.is_empty() {
+        s.push_str(&format!(" ({})", error.path));
+    }
+    s.push('\n');
+    s.push_str(&format!("{} {}|{}\n", gutter, blue, reset));
+    s.push_str(&format!("{}{} |{} {}\n", blue, number, reset, line));
+    s.push_str(&format!("{} {}|{} {}{}^{}\n", gutter, blue, reset, " ".repeat(caret), red, reset));
+    if let Some(hint) = hint(error.kind) {
+        s.push_str(&format!("{} {}={} {}hint{}: {}\n", gutter, blue, reset, cyan, reset, hint));
+    }
+    return s;
+}
+
+/// Short advice on the usual cause of each kind of error.
+pub fn hint(kind: DataErrorKind) -> Option<&'static str> {
+    let hint = match kind {
+        DataErrorKind::Io => return None,
+        DataErrorKind::InvalidUtf8 => "the input must be encoded as UTF-8",
+        DataErrorKind::InvalidUtf16 => "the input looks like UTF-16 but holds an unpaired surrogate or an odd number of bytes",
+        DataErrorKind::InvalidUtf32 => "the input looks like UTF-32 but holds a value that is not a character or a partial one",
+        DataErrorKind::UnexpectedEnd => "the input ends early, check for a missing closing quote, '}' or ']'",
+        DataErrorKind::UnexpectedCharacter => "a value was expected here",
+        DataErrorKind::ExpectedMap => "the document must be a map starting with '{'",
+        DataErrorKind::QuoteInKey => "unquoted key containing '\"', quote the whole key and escape the quote as \\\"",
+        DataErrorKind::ExpectedColon => "expected ':' between the key and its value",
+        DataErrorKind::ExpectedMapSeparator => "expected ',' or '}', check for a missing comma after the previous entry",
+        DataErrorKind::ExpectedListSeparator => "expected ',' or ']', check for a missing comma after the previous item",
+        DataErrorKind::InvalidEscape => "the valid escapes are \\\" \\\\ \\/ \\b \\f \\n \\r \\t and \\uXXXX",
+        DataErrorKind::LoneSurrogate => "a \\uD800-\\uDBFF escape must be directly followed by a \\uDC00-\\uDFFF escape",
+        DataErrorKind::UnquotedKey => "keys must be enclosed in double quotes",
+        DataErrorKind::UnquotedString => "strings must be enclosed in double quotes",
+        DataErrorKind::ControlCharacter => "control characters in strings must be escaped, e.g. as \\n or \\t",
+        DataErrorKind::InvalidNumber => "numbers are written as e.g. 42, -0.5 or 1e10",
+        DataErrorKind::NonFiniteNumber => "Infinity and NaN are not allowed by these parse options",
+        DataErrorKind::NumberOutOfRange => "the number is too large to be represented",
+        DataErrorKind::InvalidDate => "dates are written as e.g. 2024-08-25 or 2024-08-25T12:15:28Z",
+        DataErrorKind::DateOutOfRange => "the date has the right shape but does not exist in the calendar",
+        DataErrorKind::DepthLimitExceeded => "maps and lists are nested deeper than the configured limit",
+        DataErrorKind::StringTooLong => "the string is longer than the configured limit",
+        DataErrorKind::TooManyEntries => "the container has more entries than the configured limit",
+        DataErrorKind::InputTooLarge => "the input is larger than the configured limit",
+        DataErrorKind::DuplicateKey => "each key may occur only once in a map",
+        DataErrorKind::TrailingContent => "only one document is allowed, use `documents` to read a stream of them"
+    };
+    return Some(hint);
+}
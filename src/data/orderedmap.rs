@@ -0,0 +1,255 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// Size above which a map indexes its keys. Smaller maps, the common case, skip hashing, the copy
+/// of every key the index holds and the index itself.
+const INDEXED: usize = 16;
+
+/// Map that iterates in insertion order, or in key order once sorted. Entries are held in a `Vec`,
+/// searched directly while there are few of them; past `INDEXED` entries a hash index from each key
+/// to its position keeps lookup O(1). Replacing the value of an existing key keeps its position.
+#[derive(Clone)]
+pub struct OrderedMap<K, V> {
+    entries: Vec<(K, V)>,
+    /// Boxed to keep maps small, as most never need one.
+    #[allow(clippy::box_collection)]
+    index: Option<Box<HashMap<K, usize>>>,
+    sorted: bool
+}
+
+#[allow(dead_code)]
+impl<K: Hash + Eq + Ord + Clone, V> OrderedMap<K, V> {
+    pub fn new() -> Self {
+        Self { entries: Vec::new(), index: None, sorted: false }
+    }
+
+    /// An empty map that keeps its entries in key order. Inserting a new key is then O(n), as the
+    /// entries after it move up.
+    pub fn sorted() -> Self {
+        Self { sorted: true, ..Self::new() }
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Puts the entries in key order and keeps them that way from then on.
+    pub fn sort(&mut self) {
+        if !self.sorted {
+            self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+            self.reindex(0);
+            self.sorted = true;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.find(k).map(|i| &self.entries[i].1)
+    }
+
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        match self.find(k) {
+            Some(i) => return Some(&mut self.entries[i].1),
+            None => return None
+        }
+    }
+
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.find(k).is_some()
+    }
+
+    /// Inserts or replaces the value under `k`, returning the value it replaced.
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        if let Some(i) = self.find(&k) {
+            return Some(std::mem::replace(&mut self.entries[i].1, v));
+        }
+        if self.sorted {
+            let i = self.entries.partition_point(|(key, _)| *key < k);
+            self.entries.insert(i, (k, v));
+            self.reindex(i);
+        } else {
+            self.entries.push((k, v));
+            self.reindex(self.entries.len() - 1);
+        }
+        return None;
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator + Clone {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    fn find<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        match &self.index {
+            Some(index) => return index.get(k).copied(),
+            None => return self.entries.iter().position(|(key, _)| key.borrow() == k)
+        }
+    }
+
+    /// Brings the index up to date from entry `from` on, or from the start when the map has just
+    /// grown enough to need one.
+    fn reindex(&mut self, from: usize) {
+        if self.entries.len() <= INDEXED {
+            return;
+        }
+        let from = if self.index.is_none() { 0 } else { from };
+        let index = self.index.get_or_insert_with(Default::default);
+        for (i, (k, _)) in self.entries.iter().enumerate().skip(from) {
+            match index.get_mut(k) {
+                Some(pos) => *pos = i,
+                None => {
+                    index.insert(k.clone(), i);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq + Ord + Clone, V> Default for OrderedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maps are equal when they hold the same entries, whatever their order.
+impl<K: Hash + Eq + Ord + Clone, V: PartialEq> PartialEq for OrderedMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.entries.iter().map(|(k, v)| (k, v))).finish()
+    }
+}
+
+impl<K, V> IntoIterator for OrderedMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OrderedMap, INDEXED};
+
+    /// Distinct keys out of key order, so that insertion order and key order differ.
+    fn keys(n: usize) -> Vec<String> {
+        return (0..n).map(|i| format!("k{:02}", (i * 7) % n)).collect();
+    }
+
+    fn filled(keys: &[String], map: OrderedMap<String, usize>) -> OrderedMap<String, usize> {
+        let mut map = map;
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(map.insert(k.clone(), i), None);
+        }
+        return map;
+    }
+
+    fn order(map: &OrderedMap<String, usize>) -> Vec<String> {
+        return map.iter().map(|(k, _)| k.clone()).collect();
+    }
+
+    #[test]
+    fn iterates_in_insertion_order_below_and_above_the_index_size() {
+        for n in [3, INDEXED, INDEXED + 1, 40] {
+            let keys = keys(n);
+            let map = filled(&keys, OrderedMap::new());
+            assert_eq!(order(&map), keys, "{} entries", n);
+            assert_eq!(map.index.is_some(), n > INDEXED);
+            for (i, k) in keys.iter().enumerate() {
+                assert_eq!(map.get(k.as_str()), Some(&i));
+            }
+            assert_eq!(map.get("missing"), None);
+            assert!(!map.contains_key("k99"));
+        }
+    }
+
+    #[test]
+    fn replacing_a_value_keeps_its_position() {
+        for n in [5, INDEXED + 1, 40] {
+            let keys = keys(n);
+            let mut map = filled(&keys, OrderedMap::new());
+            assert_eq!(map.insert(keys[2].clone(), 100), Some(2));
+            assert_eq!(map.insert(keys[n - 1].clone(), 200), Some(n - 1));
+            *map.get_mut(keys[0].as_str()).unwrap() = 300;
+            assert_eq!(order(&map), keys);
+            assert_eq!(map.len(), n);
+            assert_eq!((map.get(keys[2].as_str()), map.get(keys[n - 1].as_str()), map.get(keys[0].as_str())), (Some(&100), Some(&200), Some(&300)));
+        }
+    }
+
+    #[test]
+    fn sorted_maps_keep_key_order() {
+        for n in [5, INDEXED + 1, 40] {
+            let keys = keys(n);
+            let mut expected = keys.clone();
+            expected.sort();
+
+            let mut map = filled(&keys, OrderedMap::sorted());
+            assert_eq!(order(&map), expected);
+            assert_eq!(map.insert(keys[3].clone(), 100), Some(3));
+            assert_eq!(order(&map), expected);
+            for (i, k) in keys.iter().enumerate() {
+                assert_eq!(map.get(k.as_str()), Some(if i == 3 { &100 } else { &i }));
+            }
+
+            // Sorting a map later, even one already indexed, keeps it sorted as it grows.
+            let mut map = filled(&keys, OrderedMap::new());
+            map.sort();
+            assert!(map.is_sorted());
+            assert_eq!(order(&map), expected);
+            map.insert(String::from("a"), 1000);
+            map.insert(String::from("k99"), 1001);
+            assert_eq!(order(&map).first().map(|k| k.as_str()), Some("a"));
+            assert_eq!(order(&map).last().map(|k| k.as_str()), Some("k99"));
+            for (i, k) in keys.iter().enumerate() {
+                assert_eq!(map.get(k.as_str()), Some(&i));
+            }
+            assert_eq!((map.get("a"), map.get("k99")), (Some(&1000), Some(&1001)));
+        }
+    }
+
+    #[test]
+    fn maps_with_the_same_entries_are_equal_in_any_order() {
+        let keys = keys(INDEXED + 4);
+        let mut reversed = keys.clone();
+        reversed.reverse();
+        let a = filled(&keys, OrderedMap::new());
+        let mut b = OrderedMap::new();
+        for k in reversed.iter() {
+            b.insert(k.clone(), keys.iter().position(|key| key == k).unwrap());
+        }
+        assert_eq!(a, b);
+        b.insert(keys[0].clone(), 99);
+        assert_ne!(a, b);
+    }
+}
@@ -1,408 +1,797 @@
+#[cfg(feature = "async")]
+mod asyncreader;
+mod documents;
+mod encoding;
+mod borrowedparser;
+mod eventreader;
+mod lexer;
 mod macros;
-mod serialreader;
+mod parseoptions;
+mod source;
 mod stringreader;
+mod treebuilder;
 
-use super::datalist;
 use super::datamap;
+use super::BorrowedValue;
 use super::DataError;
+use super::DataErrorKind;
 use super::DataValue;
 use super::Date;
 use super::Number;
+use super::Position;
+use super::{Span, Spans};
 use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
 use chrono::TimeZone;
-use chrono::Timelike;
 use chrono::Utc;
-use datalist::DataList;
 use datamap::DataMap;
-use serialreader::SerialReader;
+#[cfg(feature = "async")]
+use asyncreader::{AsyncLexer, PushSource};
+use borrowedparser::BorrowedParser;
+use lexer::{Halt, Lexer};
+use source::{ReadSource, SliceSource, Source};
+use std::io::Read;
 use stringreader::StringReader;
+use treebuilder::TreeBuilder;
 use macros::*;
 
+pub use documents::Documents;
+pub use encoding::{detect_encoding, Encoding};
+pub use eventreader::{Event, EventReader};
+pub use parseoptions::{DateDetection, DuplicateKeyPolicy, InvalidDatePolicy, ParseOptions};
+
 #[allow(dead_code)]
 pub fn parse(s: &str) -> Result<DataValue, DataError> {
-    let mut reader = SerialReader::new(s);
-    let value: DataValue = parse_from_reader(&mut reader)?;
-    return Ok(value);
+    return parse_with(s, &ParseOptions::default());
+}
+
+/// Parses raw bytes in UTF-8, UTF-16 or UTF-32, as told by `detect_encoding`, skipping any byte
+/// order mark. Offsets in errors are into `bytes`, whatever the encoding.
+#[allow(dead_code)]
+pub fn parse_bytes(bytes: &[u8]) -> Result<DataValue, DataError> {
+    return parse_bytes_with(bytes, &ParseOptions::default());
+}
+
+#[allow(dead_code)]
+pub fn parse_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<DataValue, DataError> {
+    let (encoding, bom) = detect_encoding(bytes);
+    if encoding == Encoding::Utf8 {
+        return parse_source(SliceSource::new(&bytes[bom..], options.limits.max_input_size), options).map_err(|mut e| {
+            e.position.offset += bom;
+            e
+        });
+    }
+    let text = encoding::decode(bytes, encoding, bom, options.limits.max_input_size)?;
+    return parse_with(&text, options).map_err(|mut e| {
+        e.position.offset = encoding::input_offset(&text, e.position.offset, encoding, bom);
+        e
+    });
+}
+
+#[allow(dead_code)]
+pub fn parse_with(s: &str, options: &ParseOptions) -> Result<DataValue, DataError> {
+    return parse_source(SliceSource::new(s.as_bytes(), options.limits.max_input_size), options);
 }
 
 pub fn parse_map(s: &str) -> Result<DataMap, DataError> {
-    let mut reader = SerialReader::new(s);
-    let map: DataMap = parse_map_from_reader(&mut reader)?;
+    return parse_map_with(s, &ParseOptions::default());
+}
+
+pub fn parse_map_with(s: &str, options: &ParseOptions) -> Result<DataMap, DataError> {
+    return parse_map_source(SliceSource::new(s.as_bytes(), options.limits.max_input_size), options);
+}
+
+/// Parses a value from any byte source, through a buffer that holds little more than the token
+/// being read, so that the input never needs to be held in memory as a whole.
+#[allow(dead_code)]
+pub fn parse_reader<R: Read>(r: R) -> Result<DataValue, DataError> {
+    return parse_reader_with(r, &ParseOptions::default());
+}
+
+pub fn parse_reader_with<R: Read>(r: R, options: &ParseOptions) -> Result<DataValue, DataError> {
+    return parse_source(ReadSource::new(r, options.limits.max_input_size), options);
+}
+
+#[allow(dead_code)]
+pub fn parse_map_reader<R: Read>(r: R) -> Result<DataMap, DataError> {
+    return parse_map_reader_with(r, &ParseOptions::default());
+}
+
+pub fn parse_map_reader_with<R: Read>(r: R, options: &ParseOptions) -> Result<DataMap, DataError> {
+    return parse_map_source(ReadSource::new(r, options.limits.max_input_size), options);
+}
+
+fn parse_source<S: Source>(source: S, options: &ParseOptions) -> Result<DataValue, DataError> {
+    let mut lexer = Lexer::new(source, options);
+    let value = parse_from_reader(&mut lexer)?;
+    if !options.allow_trailing_content {
+        lexer.finish()?;
+    }
+    return Ok(value);
+}
+
+fn parse_map_source<S: Source>(source: S, options: &ParseOptions) -> Result<DataMap, DataError> {
+    let mut lexer = Lexer::new(source, options);
+    let mut builder = TreeBuilder::new(options.duplicate_keys);
+    let map = lexer.block(|lexer| build_map(lexer, &mut builder))?;
+    if !options.allow_trailing_content {
+        lexer.finish()?;
+    }
     return Ok(map);
 }
 
-fn parse_from_reader(reader: &mut SerialReader) -> Result<DataValue, DataError> {
-    while reader.has_more() {
-        let c = reader.next();
-        let spaceequiv = c == ' ' || c == '\r' || c == '\n' || c == '\t';
-        if !spaceequiv {
-            reader.back();
-            match c {
-                '{' => return Ok(DataValue::DataMap(parse_map_from_reader(reader)?)),
-                '[' => return Ok(DataValue::DataList(parse_list_from_reader(reader)?)),
-                _ => return Ok(parse_literal_from_reader(reader)?),
-            }
-        }
+/// Parses a value from a tokio `AsyncRead` without buffering the whole input first. The input is
+/// read on the calling task as the parser needs it, and the parser picks up where it stopped each
+/// time more arrives, so no thread is held while waiting on a slow sender.
+#[cfg(feature = "async")]
+#[allow(dead_code)]
+pub async fn parse_async<R: tokio::io::AsyncRead + Unpin>(r: R) -> Result<DataValue, DataError> {
+    return parse_async_with(r, &ParseOptions::default()).await;
+}
+
+#[cfg(feature = "async")]
+pub async fn parse_async_with<R: tokio::io::AsyncRead + Unpin>(r: R, options: &ParseOptions) -> Result<DataValue, DataError> {
+    let mut lexer = AsyncLexer::new(r, Lexer::new(PushSource::new(options.limits.max_input_size), options));
+    let mut builder = TreeBuilder::new(options.duplicate_keys);
+    let value = lexer.drive(|lexer| build(lexer, &mut builder)).await?;
+    if !options.allow_trailing_content {
+        lexer.drive(Lexer::poll_finish).await?;
     }
-    return Err(DataError { message : String::from("Unexpected end of string")})
+    return Ok(value);
 }
 
-enum MapDeserialState {  BeforeBrace, BeforeKey, InKey, AfterKey, BeforeValue, AfterValue }
+#[cfg(feature = "async")]
+#[allow(dead_code)]
+pub async fn parse_map_async<R: tokio::io::AsyncRead + Unpin>(r: R) -> Result<DataMap, DataError> {
+    return parse_map_async_with(r, &ParseOptions::default()).await;
+}
 
-fn parse_map_from_reader(reader: &mut SerialReader) -> Result<DataMap, DataError> {
-    let mut state = MapDeserialState::BeforeBrace;
-    let mut key: String = String::new();
-    let mut inquote: bool = false;
-    let mut map = DataMap::new();
-    while reader.has_more() {
-        let c = reader.next();
-        let spaceequiv = c == ' ' || c == '\r' || c == '\n' || c == '\t';
-        match state {
-            MapDeserialState::BeforeBrace => {
-                if !spaceequiv {
-                    if c == '{' {
-                        state = MapDeserialState::BeforeKey;
-                    } else {
-                        reader.back();
-                        return Err(DataError { message : format!("Expecting '{{' at line {}, col {}", reader.row, reader.col)})
-                    }
-                }
-            },
-            MapDeserialState::BeforeKey => {
-                if !spaceequiv {
-                    if c == '"' {
-                        inquote = true;
-                    } else {
-                        key.push(c);
-                    }
-                    state = MapDeserialState::InKey;
-                }
-            },
-            MapDeserialState::InKey => {
-                if inquote {
-                    if c == '"' {
-                        inquote = false;
-                        state = MapDeserialState::AfterKey;
-                    } else {
-                        key.push(c);
-                    }
-                } else {
-                    if spaceequiv {
-                        state = MapDeserialState::AfterKey;
-                    } else if c == ':' {
-                        state = MapDeserialState::BeforeValue;
-                    } else if c == '"' {
-                        return Err(DataError { message : format!("Unexpected '\"' at line {}, col {}", reader.row, reader.col)})
-                    } else {
-                        key.push(c);
-                    }
-                }
-            },
-            MapDeserialState::AfterKey => {
-                if !spaceequiv {
-                    if c == ':' {
-                        state = MapDeserialState::BeforeValue;
-                    } else {
-                        return Err(DataError { message : format!("Unexpected character at line {}, col {}", reader.row, reader.col)})
-                    }
-                }
-            },
-            MapDeserialState::BeforeValue => {
-                if !spaceequiv {
-                    reader.back();
-                    let value = parse_from_reader(reader)?;
-                    map.put(&key, value);
-                    key.clear();
-                    state = MapDeserialState::AfterValue;
-                }
+#[cfg(feature = "async")]
+pub async fn parse_map_async_with<R: tokio::io::AsyncRead + Unpin>(r: R, options: &ParseOptions) -> Result<DataMap, DataError> {
+    let mut lexer = AsyncLexer::new(r, Lexer::new(PushSource::new(options.limits.max_input_size), options));
+    let mut builder = TreeBuilder::new(options.duplicate_keys);
+    let map = lexer.drive(|lexer| build_map(lexer, &mut builder)).await?;
+    if !options.allow_trailing_content {
+        lexer.drive(Lexer::poll_finish).await?;
+    }
+    return Ok(map);
+}
+
+/// Parses a value held in memory without copying its strings and keys, which are borrowed from
+/// `s` unless they contain escape sequences. Accepts the same syntax as `parse`.
+#[allow(dead_code)]
+pub fn parse_borrowed(s: &str) -> Result<BorrowedValue<'_>, DataError> {
+    return parse_borrowed_with(s, &ParseOptions::default());
+}
+
+#[allow(dead_code)]
+pub fn parse_borrowed_with<'a>(s: &'a str, options: &ParseOptions) -> Result<BorrowedValue<'a>, DataError> {
+    return BorrowedParser::new(s, options).parse();
+}
+
+/// Parses a value and records the span of every value and key in it, so that problems found
+/// after parsing, e.g. by validation, can be reported against the input.
+#[allow(dead_code)]
+pub fn parse_with_spans(s: &str, options: &ParseOptions) -> Result<(DataValue, Spans), DataError> {
+    let mut events = Lexer::new(SliceSource::new(s.as_bytes(), options.limits.max_input_size), options);
+    let mut builder = TreeBuilder::new(options.duplicate_keys);
+    let mut spans = Spans::new();
+    let mut open: Vec<Position> = Vec::new();
+    let value = loop {
+        let event = match events.next_event()? {
+            Some(event) => event,
+            None => return Err(DataError::new(DataErrorKind::UnexpectedEnd, "Unexpected end of input", events.current_position()))
+        };
+        let span = Span { start: events.event_position(), end: events.event_end() };
+        match event {
+            Event::StartMap | Event::StartList => open.push(span.start),
+            Event::EndMap | Event::EndList => {
+                let start = open.pop().unwrap_or(span.start);
+                spans.insert_value(events.path(), Span { start, end: span.end });
             },
-            MapDeserialState::AfterValue => {
-                if !spaceequiv {
-                    if c == ',' {
-                        state = MapDeserialState::BeforeKey;
-                    } else if c == '}' {
-                        return Ok(map);
-                    } else {
-                        return Err(DataError { message : format!("Unexpected character at line {}, col {}", reader.row, reader.col)})
-                    }
-                }
-            }                                  
+            Event::Key(_) => spans.insert_key(events.path(), span),
+            Event::Value(_) => spans.insert_value(events.path(), span)
+        }
+        match builder.accept(event, span.start) {
+            Ok(Some(value)) => break value,
+            Ok(None) => (),
+            Err(e) => return Err(e.with_path(events.path()))
         }
+    };
+    if !options.allow_trailing_content {
+        events.finish()?;
     }
-    return Err(DataError { message : String::from("String unexpectedly ended")})
+    return Ok((value, spans));
 }
 
-enum ListDeserialState {  BeforeBracket, BeforeValue, AfterValue }
+/// Returns a pull parser over the input that yields `Event`s instead of building a tree.
+#[allow(dead_code)]
+pub fn events<R: Read>(r: R) -> EventReader<R> {
+    return EventReader::new(r);
+}
 
-fn parse_list_from_reader(reader: &mut SerialReader) -> Result<DataList, DataError> {
-    let mut state: ListDeserialState = ListDeserialState::BeforeBracket;
-    let mut list = DataList::new();
-    while reader.has_more() {
-        let c = reader.next();
-        let spaceequiv = c == ' ' || c == '\r' || c == '\n' || c == '\t';
-        match state {
-            ListDeserialState::BeforeBracket => {
-                if !spaceequiv {
-                    if c == '[' {
-                        state = ListDeserialState::BeforeValue;
-                    } else {
-                        reader.back();
-                        return Err(DataError { message : format!("Expected [ at line {}, col {}", reader.row, reader.col)})
-                    }
-                }
-            },
-            ListDeserialState::BeforeValue => {
-                if !spaceequiv {
-                    reader.back();
-                    let value = parse_from_reader(reader)?;
-                    list.push(value);
-                    state = ListDeserialState::AfterValue;
-                }
-            },
-            ListDeserialState::AfterValue => {
-                if !spaceequiv {
-                    if c == ',' {
-                        state = ListDeserialState::BeforeValue;
-                    } else if c == ']' {
-                        return Ok(list);
-                    } else {
-                        return Err(DataError { message : format!("Expected ] at line {}, col {}", reader.row, reader.col)})
-                    }
-                }
-            }                                  
-        }
-    }
-    return Err(DataError { message : String::from("String unexpectedly ended")})
-}
-
-enum LiteralDeserialState {  BeforeValue, InValue }
-
-fn parse_literal_from_reader(reader: &mut SerialReader) -> Result<DataValue, DataError> {
-    let mut state = LiteralDeserialState::BeforeValue;
-    let mut value: String = String::new();
-    let mut inquote: bool = false;
-    let mut escaping: bool = false;
-    let mut hasquotes: bool = false;
-    let mut done: bool = false;
-    while reader.has_more() && !done {
-        let c = reader.next();
-        let spaceequiv = c == ' ' || c == '\r' || c == '\n' || c == '\t';
-        match state {
-            LiteralDeserialState::BeforeValue => {
-                if !spaceequiv {
-                    if c == '"' {
-                        inquote = true;
-                        hasquotes = true;
-                    } else {
-                        value.push(c);  
-                    }
-                    state = LiteralDeserialState::InValue;
-                }
+#[allow(dead_code)]
+pub fn events_with<R: Read>(r: R, options: &ParseOptions) -> EventReader<R> {
+    return EventReader::with_options(r, options);
+}
+
+/// Parses as much of the input as possible, recovering from each error at the next `,`, `}` or `]`
+/// instead of stopping. Returns the best effort value, with the entries that failed to parse left
+/// out, together with every error found. The value is `DataValue::None` when nothing could be read.
+#[allow(dead_code)]
+pub fn parse_recovering(s: &str) -> (DataValue, Vec<DataError>) {
+    return parse_recovering_with(s, &ParseOptions::default());
+}
+
+#[allow(dead_code)]
+pub fn parse_recovering_with(s: &str, options: &ParseOptions) -> (DataValue, Vec<DataError>) {
+    let mut events = Lexer::new(SliceSource::new(s.as_bytes(), options.limits.max_input_size), options);
+    let mut errors = Vec::new();
+    let value = recover_from_reader(&mut events, &mut errors);
+    if !options.allow_trailing_content && errors.is_empty() {
+        if let Err(e) = events.finish() {
+            errors.push(e);
+        }
+    }
+    return (value, errors);
+}
+
+/// Returns an iterator over a stream of back to back values, such as newline delimited JSON, yielding
+/// each value with the byte offset at which it starts.
+#[allow(dead_code)]
+pub fn documents<R: Read>(r: R) -> Documents<R> {
+    return Documents::new(EventReader::new(r));
+}
+
+#[allow(dead_code)]
+pub fn documents_with<R: Read>(r: R, options: &ParseOptions) -> Documents<R> {
+    return Documents::new(EventReader::with_options(r, options));
+}
+
+fn parse_from_reader<S: Source>(lexer: &mut Lexer<S>) -> Result<DataValue, DataError> {
+    let mut builder = TreeBuilder::new(lexer.options().duplicate_keys);
+    return lexer.block(|lexer| build(lexer, &mut builder));
+}
+
+/// Feeds events to `builder` until the value being read is complete. Events are only taken whole,
+/// so this can be called again after `Halt::Incomplete` to carry on where it stopped.
+fn build<S: Source>(lexer: &mut Lexer<S>, builder: &mut TreeBuilder) -> Result<DataValue, Halt> {
+    while let Some(event) = lexer.poll_event()? {
+        match builder.accept(event, lexer.event_position()) {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => (),
+            Err(e) => return Err(Halt::Error(e.with_path(lexer.path())))
+        }
+    }
+    return Err(Halt::Error(DataError::new(DataErrorKind::UnexpectedEnd, "Unexpected end of input", lexer.current_position())));
+}
+
+/// Like `build`, for a value that must be a map.
+fn build_map<S: Source>(lexer: &mut Lexer<S>, builder: &mut TreeBuilder) -> Result<DataMap, Halt> {
+    if builder.is_empty() {
+        match lexer.poll_event()? {
+            Some(Event::StartMap) => builder.accept(Event::StartMap, lexer.event_position())?,
+            _ => return Err(Halt::Error(DataError::new(DataErrorKind::ExpectedMap, "Expecting '{'", lexer.event_position())))
+        };
+    }
+    match build(lexer, builder)? {
+        DataValue::DataMap(map) => return Ok(map),
+        _ => return Err(Halt::Error(DataError::new(DataErrorKind::ExpectedMap, "Expecting '{'", Position::start())))
+    }
+}
+
+fn recover_from_reader<S: Source>(events: &mut Lexer<S>, errors: &mut Vec<DataError>) -> DataValue {
+    let mut builder = TreeBuilder::new(events.options().duplicate_keys);
+    loop {
+        match events.next_event() {
+            Ok(Some(event)) => match builder.accept(event, events.event_position()) {
+                Ok(Some(value)) => return value,
+                Ok(None) => (),
+                Err(e) => errors.push(e.with_path(events.path()))
             },
-            LiteralDeserialState::InValue => {
-                if inquote {
-                    if escaping {
-                        match c {
-                            '\\' => value.push('\\'),
-                            'n' => value.push('\n'),
-                            'r' => value.push('\r'),
-                            't' => value.push('\t'),
-                            '/' => value.push('/'),
-                            '"' => value.push('\"'),
-                            _ => ()
-                        }
-                        escaping = false;
-                    } else if c == '\\' {
-                        escaping = true
-                    } else if c == '"' {
-                        inquote = false;
-                        done = true;
-                    } else {
-                        value.push(c);
-                    }
-                } else {
-                    if spaceequiv || c == '}' || c == ']' || c == ','  {
-                        reader.back();
-                        done = true;
-                    } else {
-                        value.push(c);
-                    }
+            Ok(None) => break,
+            Err(e) => {
+                let recovered = events.recover(&e);
+                errors.push(e);
+                if !recovered {
+                    break;
                 }
-            }                                  
+            }
         }
     }
-    if hasquotes {
-        let dt_res = parse_date(&value);
+    return builder.close();
+}
+
+/// Interprets the text of a complete literal starting at `start`. Returns `None` when it is a
+/// plain string, leaving the caller to take ownership of the text.
+fn literal_value(value: &str, quoted: bool, start: Position, options: &ParseOptions, detection: DateDetection) -> Result<Option<DataValue>, DataError> {
+    if quoted {
+        let digits = value.len() >= 10 && value.as_bytes()[..4].iter().all(u8::is_ascii_digit);
+        if detection == DateDetection::Off || !digits {
+            return Ok(None);
+        }
+        let dt_res = parse_date_with(value, detection);
         match dt_res {
-            Result::Ok(dt) => return Ok(DataValue::Date(dt)),
-            _ => return Ok(DataValue::String(value))
+            Result::Ok(dt) => return Ok(Some(DataValue::Date(dt))),
+            Result::Err(e) if e.kind == DataErrorKind::DateOutOfRange && options.invalid_dates == InvalidDatePolicy::Error => {
+                return Err(DataError::new(e.kind, &e.message, start));
+            },
+            _ => return Ok(None)
         }
     } else {
         if value.eq("true") {
-            return Ok(DataValue::Bool(true));
+            return Ok(Some(DataValue::Bool(true)));
         } else if value.eq("false") {
-            return Ok(DataValue::Bool(false));
-        } else if value.eq("mull") {
-            return Ok(DataValue::Null);
+            return Ok(Some(DataValue::Bool(false)));
+        } else if value.eq("null") {
+            return Ok(Some(DataValue::Null));
         } else {
-            let num_res = parse_number(&value);
+            let num_res = parse_number_with(value, options);
             match num_res {
-                Result::Ok(n) => return Ok(DataValue::Number(n)),
-                _ => return Ok(DataValue::String(value))
+                Result::Ok(n) => return Ok(Some(DataValue::Number(n))),
+                Result::Err(e) if options.allow_unquoted_strings && e.kind != DataErrorKind::NumberOutOfRange && !cut_short(value, &e) => return Ok(None),
+                Result::Err(e) => {
+                    let numeric = value.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.');
+                    if numeric || e.kind == DataErrorKind::NonFiniteNumber {
+                        return Err(DataError::new(e.kind, &e.message, start.advance(e.position)));
+                    }
+                    return Err(DataError::new(DataErrorKind::UnquotedString, "Unquoted string", start));
+                }
             }
         }
     }
 }
 
+/// Whether the number grammar failed only for want of more text, as in `1e` or `0x`, so that the
+/// literal is a malformed number rather than an unquoted string.
+fn cut_short(value: &str, error: &DataError) -> bool {
+    return error.position.offset >= value.len() && value.bytes().any(|b| b.is_ascii_digit());
+}
+
+/// Parses a number literal: an optional sign, integer digits, an optional fraction and an optional
+/// exponent. Integers that do not fit an `i64` become `Number::UInt` when positive and within `u64`,
+/// otherwise `Number::Float`. Floats are correctly rounded; values beyond the `f64` range are an error.
+#[allow(dead_code)]
 pub fn parse_number(s: &str) -> Result<Number, DataError> {
-    if s.eq("Infinity") { return Ok(Number::PositiveInfinity) }
-    if s.eq("-Infinity") { return Ok(Number::NegativeInfinity) }
-    if s.eq("NaN") { return Ok(Number::NaN) }
+    return parse_number_with(s, &ParseOptions::default());
+}
+
+pub fn parse_number_with(s: &str, options: &ParseOptions) -> Result<Number, DataError> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if (1..=18).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit()) && (digits.len() == 1 || !digits.starts_with('0')) {
+        let n = digits.bytes().fold(0, |n, b| (10 * n) + (b - b'0') as i64);
+        return Ok(Number::Int(if digits.len() < s.len() { -n } else { n }));
+    }
+    let mut reader = StringReader::new(s, DataErrorKind::InvalidNumber);
+    match s {
+        "Infinity" | "+Infinity" | "-Infinity" | "NaN" if !options.allow_non_finite_numbers => {
+            return Err(DataError::new(DataErrorKind::NonFiniteNumber, "Non-finite numbers are not allowed", Position::start()));
+        },
+        "+Infinity" if !options.allow_leading_plus => { reader.next(); parse_err!(reader) },
+        "Infinity" | "+Infinity" => return Ok(Number::PositiveInfinity),
+        "-Infinity" => return Ok(Number::NegativeInfinity),
+        "NaN" => return Ok(Number::NaN),
+        _ => ()
+    }
     let mut neg = false;
-    let mut int: i64 = 0;
-    let mut dec: f64 = 0.0;
-    let mut reader = StringReader { chars: s.chars() };
-    let endchar = 0u8 as char;
+    let mut isfloat = false;
     let mut c = reader.next();
-    if c == '-' {
-        neg = true;
+    if c == '-' || c == '+' {
+        if c == '+' && !options.allow_leading_plus { parse_err!(reader) }
+        neg = c == '-';
         c = reader.next();
     }
-    while c != endchar && c != '.' {
-        assert_numeric!(c);
-        int = (10 * int) + to_int!(c);
+    let bare = options.allow_bare_decimal_point;
+    if !(bare && c == '.') {
+        assert_numeric!(reader, c);
+    }
+    if c == '0' {
         c = reader.next();
+        if (c == 'x' || c == 'X') && options.allow_hex_numbers {
+            return parse_hex(&mut reader, s, neg);
+        }
+        if c.is_ascii_digit() && !options.allow_leading_zeros { parse_err!(reader) }
     }
-    if c == endchar {
-        if neg {int *= -1;}
-        return Ok(Number::Int(int));
-    } else {
-        let mut div: f64 = 10.0;
+    let mut intdigits = false;
+    while c.is_ascii_digit() {
+        intdigits = true;
+        c = reader.next();
+    }
+    if c == '.' {
+        isfloat = true;
+        c = reader.next();
+        if !(bare && intdigits && !c.is_ascii_digit()) {
+            assert_numeric!(reader, c);
+        }
+        while c.is_ascii_digit() {
+            c = reader.next();
+        }
+    }
+    if c == 'e' || c == 'E' {
+        isfloat = true;
         c = reader.next();
-        while c != endchar {
-            assert_numeric!(c);
-            dec += to_float!(c) / div;
-            div *= 10.0;
+        if c == '-' || c == '+' {
+            c = reader.next();
+        }
+        assert_numeric!(reader, c);
+        while c.is_ascii_digit() {
             c = reader.next();
         }
-        return Ok(Number::Float((int as f64) + dec))
+    }
+    if !reader.at_end() { parse_err!(reader) }
+    if !isfloat {
+        if let Ok(i) = s.parse::<i64>() {
+            return Ok(Number::Int(i));
+        }
+        if !neg {
+            if let Ok(u) = s.parse::<u64>() {
+                return Ok(Number::UInt(u));
+            }
+        }
+    }
+    match s.parse::<f64>() {
+        Ok(f) if f.is_finite() => return Ok(Number::Float(f)),
+        _ => return Err(DataError::new(DataErrorKind::NumberOutOfRange, "Number out of range", Position::start()))
+    }
+}
+
+fn parse_hex(reader: &mut StringReader, s: &str, neg: bool) -> Result<Number, DataError> {
+    let digits = s.trim_start_matches(['-', '+']);
+    let mut c = reader.next();
+    if !c.is_ascii_hexdigit() { parse_err!(reader) }
+    while c.is_ascii_hexdigit() {
+        c = reader.next();
+    }
+    if !reader.at_end() { parse_err!(reader) }
+    match u64::from_str_radix(&digits[2..], 16) {
+        Ok(u) if !neg && u > i64::MAX as u64 => return Ok(Number::UInt(u)),
+        Ok(u) if !neg => return Ok(Number::Int(u as i64)),
+        Ok(u) if u <= i64::MAX as u64 + 1 => return Ok(Number::Int((u as i64).wrapping_neg())),
+        _ => return Err(DataError::new(DataErrorKind::NumberOutOfRange, "Number out of range", Position::start()))
     }
 }
 
+#[allow(dead_code)]
 pub fn parse_date(s: &str) -> Result<Date, DataError> {
-    let mut year = 0;
-    let mut month = 0;
-    let mut day = 0;
-    let mut hour = 0;
-    let mut min = 0;
-    let mut sec = 0;
-    let mut nano = 0;
-    let mut offset = 0;
-    let mut reader = StringReader { chars: s.chars() };
+    return parse_date_with(s, DateDetection::Iso8601);
+}
+
+/// Parses a timestamp in the formats enabled by `detection`. `Iso8601` accepts
+/// `YYYY-MM-DDTHH:MM:SS[.fraction](Z|+HH[:MM]|-HH[:MM])` with up to nine fractional digits.
+/// `Extended` additionally accepts date-only values, omitted seconds, a space or lowercase `t`
+/// separator, lowercase `z`, the basic format (`20240825T121528Z`, offsets as `+HHMM`) and any
+/// number of fractional digits, truncated to nanoseconds.
+///
+/// Values of the right shape that do not name a real instant fail with `DateOutOfRange` rather than
+/// `InvalidDate`. Years are four digits, 0000 to 9999, in the proleptic Gregorian calendar. Offsets
+/// are limited to 23:59 either side of UTC. A leap second (`:60`) is accepted in the last minute of
+/// any hour, since offsets move the UTC 23:59 minute, and is held the way chrono represents leap
+/// seconds, as second 59 with a nanosecond value of one second or more.
+pub fn parse_date_with(s: &str, detection: DateDetection) -> Result<Date, DataError> {
+    let mut reader = StringReader::new(s, DataErrorKind::InvalidDate);
+    if detection == DateDetection::Off { return Err(reader.error("Date detection is off")) }
+    let extended = detection == DateDetection::Extended;
     let mut c = reader.next();
-    assert_numeric!(c);
-    year += 1000 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    year += 100 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    year += 10 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    year += 1 * to_int!(c);
-    c = reader.next();
-    assert_same!(c, '-');
-    c = reader.next();
-    assert_numeric!(c);
-    month += 10 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    month += 1 * to_int!(c);
-    c = reader.next();
-    assert_same!(c, '-');
-    c = reader.next();
-    assert_numeric!(c);
-    day += 10 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    day += 1 * to_int!(c);
-    c = reader.next();
-    assert_same!(c, 'T');
-    c = reader.next();
-    assert_numeric!(c);
-    hour += 10 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    hour += 1 * to_int!(c);
-    c = reader.next();
-    assert_same!(c, ':');
-    c = reader.next();
-    assert_numeric!(c);
-    min += 10 * to_int!(c);
-    c = reader.next();
-    assert_numeric!(c);
-    min += 1 * to_int!(c);
-    c = reader.next();
-    assert_same!(c, ':');
-    c = reader.next();
-    assert_numeric!(c);
-    sec += 10 * to_int!(c);
+    let year = read_digits(&mut reader, &mut c, 4)?;
+    let basic = extended && c.is_ascii_digit();
+    if !basic {
+        assert_same!(reader, c, '-');
+        c = reader.next();
+    }
+    let month = read_digits(&mut reader, &mut c, 2)?;
+    if !basic {
+        assert_same!(reader, c, '-');
+        c = reader.next();
+    }
+    let day = read_digits(&mut reader, &mut c, 2)?;
+    if reader.at_end() && extended && !basic {
+        return Ok(Date::NaiveDate(make_date(year, month, day)?));
+    }
+    if !(c == 'T' || (extended && (c == 't' || c == ' '))) { parse_err!(reader) }
     c = reader.next();
-    assert_numeric!(c);
-    sec += 1 * to_int!(c);
+    let hour = read_digits(&mut reader, &mut c, 2)?;
+    if !basic {
+        assert_same!(reader, c, ':');
+        c = reader.next();
+    }
+    let min = read_digits(&mut reader, &mut c, 2)?;
+    let mut sec = 0;
+    if basic && c.is_ascii_digit() {
+        sec = read_digits(&mut reader, &mut c, 2)?;
+    } else if !basic && (c == ':' || !extended) {
+        assert_same!(reader, c, ':');
+        c = reader.next();
+        sec = read_digits(&mut reader, &mut c, 2)?;
+    }
 
-    c = reader.next();
-    assert_not_end!(c);
+    let mut nano = 0;
     if c == '.' {
-        let mut nanoorder = 1;
-        loop {
-            c = reader.next();
-            assert_not_end!(c);
-            if c.is_digit(10) {
+        c = reader.next();
+        assert_numeric!(reader, c);
+        let mut digits = 0;
+        while c.is_ascii_digit() {
+            if digits < 9 {
                 nano = (10 * nano) + to_int!(c);
-                nanoorder *= 10;
-            } else if c == 'Z' || c == '+' || c == '-' {
-                nano *= 1000000000 / nanoorder;
-                break;
-            } else {
-                parse_err!()
+            } else if !extended {
+                parse_err!(reader)
             }
+            digits += 1;
+            c = reader.next();
+        }
+        while digits < 9 {
+            nano *= 10;
+            digits += 1;
         }
     }
 
-    if c == 'Z' {
-        offset = 0;
-        c = reader.next();
-        if c != 0u8 as char { parse_err!() }
+    assert_not_end!(reader);
+    let mut offset_hours = 0;
+    let mut offset_mins = 0;
+    let mut neg = false;
+    if c == 'Z' || (extended && c == 'z') {
+        reader.next();
     } else {
-        let neg = if c == '-' { true } else { false };
-        c = reader.next();
-        assert_numeric!(c);
-        offset += to_int!(c) * 600;
+        if c != '+' && c != '-' { parse_err!(reader) }
+        neg = c == '-';
         c = reader.next();
-        assert_numeric!(c);
-        offset += to_int!(c) * 60;
-        c = reader.next();
-        if c != 0u8 as char {
-            assert_same!(c, ':');
-            c = reader.next();
-            assert_numeric!(c);
-            offset += to_int!(c) * 10;
-            c = reader.next();
-            assert_numeric!(c);
-            offset += to_int!(c) * 1;
-            c = reader.next();
-            if c != 0u8 as char { parse_err!() }
+        offset_hours = read_digits(&mut reader, &mut c, 2)?;
+        if c == ':' || (extended && c.is_ascii_digit()) {
+            if c == ':' {
+                c = reader.next();
+            }
+            offset_mins = read_digits(&mut reader, &mut c, 2)?;
         }
-        offset *= 60 * (if neg { -1 } else { 1 })
     }
+    if !reader.at_end() { parse_err!(reader) }
+
+    if offset_hours > 23 || offset_mins > 59 {
+        return Err(date_range_err("Offset out of range"));
+    }
+    let offset = ((offset_hours * 60) + offset_mins) * 60 * (if neg { -1 } else { 1 });
 
+    if sec == 60 {
+        if min != 59 {
+            return Err(date_range_err("Leap second outside the last minute of an hour"));
+        }
+        sec = 59;
+        nano += 1_000_000_000;
+    }
+    let date = make_date(year, month, day)?;
+    let time = match NaiveTime::from_hms_nano_opt(hour as u32, min as u32, sec as u32, nano as u32) {
+        Some(t) => t,
+        None => return Err(date_range_err("Time out of range"))
+    };
+    let naive = NaiveDateTime::new(date, time);
     if offset == 0 {
-        let dt = Utc.with_ymd_and_hms(year as i32, month as u32, day as u32, hour as u32, min as u32, sec as u32).unwrap().with_nanosecond(nano as u32).unwrap();
-        return Ok(Date::DateTimeUtc(dt))
+        return Ok(Date::DateTimeUtc(Utc.from_utc_datetime(&naive)))
     } else {
-        let dt = FixedOffset::east_opt(offset as i32).unwrap().with_ymd_and_hms(year as i32, month as u32, day as u32, hour as u32, min as u32, sec as u32).unwrap().with_nanosecond(nano as u32).unwrap();
+        let dt = match FixedOffset::east_opt(offset as i32).and_then(|tz| tz.from_local_datetime(&naive).single()) {
+            Some(dt) => dt,
+            None => return Err(date_range_err("Offset out of range"))
+        };
         return Ok(Date::DateTimeOffset(dt))
     }
-} 
\ No newline at end of file
+}
+
+fn make_date(year: i64, month: i64, day: i64) -> Result<NaiveDate, DataError> {
+    if !(1..=12).contains(&month) {
+        return Err(date_range_err(&format!("No month {}", month)));
+    }
+    match NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32) {
+        Some(d) => return Ok(d),
+        None => return Err(date_range_err(&format!("No day {} in month {} of {}", day, month, year)))
+    }
+}
+
+fn date_range_err(message: &str) -> DataError {
+    DataError::new(DataErrorKind::DateOutOfRange, message, Position::start())
+}
+
+/// Reads exactly `n` digits starting with `c`, leaving `c` on the character that follows them.
+fn read_digits(reader: &mut StringReader, c: &mut char, n: usize) -> Result<i64, DataError> {
+    let mut value = 0;
+    for _ in 0..n {
+        assert_numeric!(reader, *c);
+        value = (10 * value) + to_int!(*c);
+        *c = reader.next();
+    }
+    return Ok(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::serializers::{serialize_with, SerializeOptions};
+    use super::parseoptions::ParseLimits;
+    use super::*;
+
+    /// Compact text of a value, or the kind, position and path of an error, so that outcomes of
+    /// different entry points can be compared.
+    fn outcome<T: super::super::SerializableData>(res: Result<T, DataError>) -> String {
+        match res {
+            Ok(v) => return serialize_with(&v, &SerializeOptions::compact()),
+            Err(e) => return format!("{:?} at {} ({})", e.kind, e.position.offset, e.path)
+        }
+    }
+
+    const SYNTAX: &[&str] = &[
+        "", " ", "null", "true", "false", "tru", "1", "-1", "+1", "007", "0", "-0", "1.5", "1e3", "1E-3",
+        "1.", ".5", "0x1F", "-0x1f", "0x", "1e400", "-1e400", "18446744073709551615", "18446744073709551616",
+        "Infinity", "-Infinity", "NaN", "1.2.3", "--1",
+        "\"\"", "\"abc\"", "\"a\\\"b\\\\c\\/d\\b\\f\\n\\r\\t\"", "\"\\u00e9\\ud83d\\ude00\"", "\"\\ud83d\"", "\"\\ude00x\"",
+        "\"\\x\"", "\"\\u12\"", "\"a\tb\"", "\"a\nb\"", "\"abc", "\"abc\\", "'single'", "\"line\\\ncont\"",
+        "\"2024-08-25T12:15:28.999+10:00\"", "\"2024-08-25T12:15:28Z\"", "\"2024-02-30T10:00:00Z\"", "\"2024-08-25\"",
+        "\"2024-08-25T12:15:28+99:99\"", "\"2024-13-01\"", "\"20240825T121528Z\"", "\"2024-08-25 12:15\"",
+        "[]", "[1, 2, 3]", "[1 2]", "[1,]", "[,1]", "[1,,2]", "[", "[1", "[1,", "[[[]]]", "[abc, def]", "[\"a\" \"b\"]",
+        "{}", "{\"a\": 1}", "{\"a\" 1}", "{\"a\": 1 \"b\": 2}", "{\"a\": 1,}", "{,}", "{\"a\":}", "{\"a\"", "{\"a\":1",
+        "{a: 1}", "{a b: 1}", "{a\"b: 1}", "{'a': 1}", "{\"a\": {\"b\": [1, {\"c\": null}]}}", "{\"a\": 1, \"a\": 2, \"a\": 3}",
+        "{\"d\": \"2024-08-25\", \"l\": [\"2024-08-25\"]}", "{\"a\tb\": 1}", "{\"a\\u0041\": 1}", "{1: 2}",
+        "// c\n[1]", "[1 /* c */, 2]", "{a/*x*/:1}", "{abc// c\n:1}", "[1] // c", "/* open", "[1, /]",
+        "[1] x", "[1] [2]", "{} ", "1 2", "\u{feff}[1]", "[\"é😀\"]", "[\"\u{7f}\"]", "{\"k\": [1, 2], \"m\": {}}"
+    ];
+
+    fn option_sets() -> Vec<ParseOptions> {
+        let mut sets = vec![ParseOptions::default(), ParseOptions::strict(), ParseOptions::json5()];
+        let limits = [
+            ParseLimits { max_depth: Some(2), ..ParseLimits::unlimited() },
+            ParseLimits { max_string_length: Some(2), ..ParseLimits::unlimited() },
+            ParseLimits { max_entries: Some(2), ..ParseLimits::unlimited() },
+            ParseLimits { max_input_size: Some(8), ..ParseLimits::unlimited() }
+        ];
+        for limits in limits {
+            sets.push(ParseOptions { limits: limits.clone(), ..ParseOptions::strict() });
+            sets.push(ParseOptions { limits, ..ParseOptions::json5() });
+        }
+        for policy in [DuplicateKeyPolicy::FirstWins, DuplicateKeyPolicy::Error, DuplicateKeyPolicy::Collect] {
+            sets.push(ParseOptions { duplicate_keys: policy, ..ParseOptions::default() });
+        }
+        sets.push(ParseOptions { invalid_dates: InvalidDatePolicy::Error, ..ParseOptions::default() });
+        sets.push(ParseOptions { date_detection: DateDetection::Off, ..ParseOptions::default() });
+        sets.push(ParseOptions { date_detection: DateDetection::Extended, ..ParseOptions::default() });
+        sets.push(ParseOptions { date_exempt_keys: ["d".to_string()].into_iter().collect(), ..ParseOptions::default() });
+        return sets;
+    }
+
+    #[test]
+    fn in_memory_and_streaming_parses_agree() {
+        for options in option_sets() {
+            for input in SYNTAX {
+                let streamed = outcome(parse_reader_with(input.as_bytes(), &options));
+                assert_eq!(outcome(parse_with(input, &options)), streamed, "parse_with {:?}", input);
+                let borrowed = parse_borrowed_with(input, &options).map(BorrowedValue::into_owned);
+                assert_eq!(outcome(borrowed), streamed, "parse_borrowed_with {:?}", input);
+                let map = outcome(parse_map_reader_with(input.as_bytes(), &options));
+                assert_eq!(outcome(parse_map_with(input, &options)), map, "parse_map_with {:?}", input);
+            }
+        }
+    }
+
+    /// `Read` that hands out at most `chunk` bytes per call.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        chunk: usize
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.chunk).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            return Ok(n);
+        }
+    }
+
+    #[test]
+    fn small_reads_parse_the_same() {
+        for options in option_sets() {
+            for input in SYNTAX {
+                let expected = outcome(parse_with(input, &options));
+                for chunk in [1, 2, 3, 5] {
+                    let streamed = parse_reader_with(Trickle { bytes: input.as_bytes(), chunk }, &options);
+                    assert_eq!(outcome(streamed), expected, "chunks of {} {:?}", chunk, input);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn characters_split_across_reads_and_buffers() {
+        // Puts each byte of the multi-byte characters in turn on the last byte of the 8192 byte buffer.
+        for pad in 8185..8195 {
+            let text = format!("{}é😀€{}", "a".repeat(pad - 2), "b".repeat(10));
+            let input = format!("[\"{}\", {{\"{}\": 1}}]", text, text);
+            let expected = outcome(parse_borrowed(&input).map(BorrowedValue::into_owned));
+            assert!(expected.contains("é😀€"));
+            for chunk in [1, 3, 1000, 8192, 10000] {
+                assert_eq!(outcome(parse_reader(Trickle { bytes: input.as_bytes(), chunk })), expected, "pad {} chunks of {}", pad, chunk);
+            }
+        }
+        let mut input = b"[\"".to_vec();
+        input.extend(std::iter::repeat_n(b'a', 8188));
+        input.extend([0xE2, 0x82, b'"', b']']);
+        for chunk in [1, 8189, 8190, 8192] {
+            let error = parse_reader(Trickle { bytes: &input, chunk }).map_err(|e| (e.kind, e.position.offset)).err();
+            assert_eq!(error, Some((DataErrorKind::InvalidUtf8, 8190)), "chunks of {}", chunk);
+        }
+    }
+
+    #[test]
+    fn unquoted_strings_do_not_swallow_malformed_numbers() {
+        for options in [ParseOptions::default(), ParseOptions::json5()] {
+            for (input, kind) in [("[1e400]", DataErrorKind::NumberOutOfRange), ("[-1e400]", DataErrorKind::NumberOutOfRange), ("[1e]", DataErrorKind::InvalidNumber), ("[-1e+]", DataErrorKind::InvalidNumber)] {
+                assert_eq!(parse_with(input, &options).map_err(|e| e.kind).err(), Some(kind), "{}", input);
+            }
+            for (input, expected) in [("[1.2.3]", "[\"1.2.3\"]"), ("[123abc]", "[\"123abc\"]"), ("[abc]", "[\"abc\"]"), ("[-]", "[\"-\"]")] {
+                assert_eq!(outcome(parse_with(input, &options)), expected);
+            }
+        }
+        let json5 = ParseOptions::json5();
+        assert_eq!(parse_with("[0x]", &json5).map_err(|e| e.kind).err(), Some(DataErrorKind::InvalidNumber));
+        assert_eq!(parse_with("[0x10000000000000000]", &json5).map_err(|e| e.kind).err(), Some(DataErrorKind::NumberOutOfRange));
+        assert_eq!(outcome(parse_with("[0x1F, 0xZZ]", &json5)), "[31,\"0xZZ\"]");
+    }
+
+    #[test]
+    fn impossible_dates_are_errors_not_panics() {
+        let inputs = [
+            "2024-02-30T10:00:00Z", "2023-02-29T10:00:00Z", "2024-13-01T10:00:00Z", "2024-00-10T10:00:00Z", "2024-08-25T24:00:00Z",
+            "2024-08-25T12:60:00Z", "2024-08-25T12:15:61Z", "2024-08-25T12:14:60Z", "2024-08-25T12:15:28+99:99", "2024-08-25T12:15:28-24:00",
+            "2024-08-25T12:15:28+10:60"
+        ];
+        for input in inputs {
+            for detection in [DateDetection::Iso8601, DateDetection::Extended] {
+                assert_eq!(parse_date_with(input, detection).map_err(|e| e.kind).err(), Some(DataErrorKind::DateOutOfRange), "{}", input);
+            }
+            let quoted = format!("[\"{}\"]", input);
+            assert_eq!(outcome(parse(&quoted)), quoted.replace(' ', ""));
+            let strict_dates = ParseOptions { invalid_dates: InvalidDatePolicy::Error, ..ParseOptions::default() };
+            assert_eq!(parse_with(&quoted, &strict_dates).map_err(|e| (e.kind, e.position.offset)).err(), Some((DataErrorKind::DateOutOfRange, 1)));
+        }
+        for input in ["2024-08-25T12:15:28+23:59", "2016-12-31T23:59:60Z", "0000-01-01T00:00:00Z", "9999-12-31T23:59:59-23:59", "2024-02-29T10:00:00Z"] {
+            assert!(parse_date(input).is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn each_limit_fails_with_its_own_kind() {
+        let cases = [
+            (ParseLimits { max_depth: Some(2), ..ParseLimits::unlimited() }, "[[1]]", "[[[1]]]", DataErrorKind::DepthLimitExceeded),
+            (ParseLimits { max_depth: Some(2), ..ParseLimits::unlimited() }, "{\"a\": {}}", "{\"a\": {\"b\": {}}}", DataErrorKind::DepthLimitExceeded),
+            (ParseLimits { max_string_length: Some(3), ..ParseLimits::unlimited() }, "[\"abc\"]", "[\"abcd\"]", DataErrorKind::StringTooLong),
+            (ParseLimits { max_string_length: Some(3), ..ParseLimits::unlimited() }, "{\"abc\": 1}", "{\"abcd\": 1}", DataErrorKind::StringTooLong),
+            (ParseLimits { max_string_length: Some(3), ..ParseLimits::unlimited() }, "[123]", "[1234]", DataErrorKind::StringTooLong),
+            (ParseLimits { max_entries: Some(2), ..ParseLimits::unlimited() }, "[1, [2, 3]]", "[1, 2, 3]", DataErrorKind::TooManyEntries),
+            (ParseLimits { max_entries: Some(2), ..ParseLimits::unlimited() }, "{\"a\": 1, \"b\": 2}", "{\"a\": 1, \"b\": 2, \"c\": 3}", DataErrorKind::TooManyEntries),
+            (ParseLimits { max_input_size: Some(9), ..ParseLimits::unlimited() }, "[1, 2, 3]", "[1, 2, 3 ]", DataErrorKind::InputTooLarge)
+        ];
+        for (limits, within, beyond, kind) in cases {
+            let options = ParseOptions { limits, ..ParseOptions::default() };
+            assert!(parse_with(within, &options).is_ok(), "{}", within);
+            assert!(parse_borrowed_with(within, &options).is_ok(), "{}", within);
+            assert_eq!(parse_with(beyond, &options).map_err(|e| e.kind).err(), Some(kind), "{}", beyond);
+            assert_eq!(parse_borrowed_with(beyond, &options).map_err(|e| e.kind).err(), Some(kind), "{}", beyond);
+        }
+    }
+
+    #[test]
+    fn surrogate_pair_escapes() {
+        for (input, expected) in [
+            ("[\"\\ud83d\\ude00\"]", "😀"), ("[\"\\uD83D\\uDE00\"]", "😀"), ("[\"a\\ud834\\udd1eb\"]", "a𝄞b"), ("[\"\\udbff\\udfff\"]", "\u{10ffff}"),
+            ("[\"\\ud800\\udc00\"]", "\u{10000}"), ("[\"\\u00e9\\uffff\"]", "é\u{ffff}")
+        ] {
+            assert_eq!(outcome(parse(input)), format!("[\"{}\"]", expected), "{}", input);
+            assert_eq!(outcome(parse_borrowed(input).map(BorrowedValue::into_owned)), outcome(parse(input)));
+        }
+        assert_eq!(outcome(parse_map("{\"\\ud83d\\ude00\": 1}")), "{\"😀\":1}");
+        for (input, offset) in [
+            ("[\"\\ud83d\"]", 2), ("[\"x\\ud83dx\"]", 3), ("[\"\\ud83d\\n\"]", 2), ("[\"\\ud83d\\ud83d\"]", 2), ("[\"\\ude00\\ud83d\"]", 2),
+            ("[\"\\ude00\"]", 2), ("{\"\\ud83d\": 1}", 2)
+        ] {
+            let error = parse_with(input, &ParseOptions::strict()).map_err(|e| (e.kind, e.position.offset)).err();
+            assert_eq!(error, Some((DataErrorKind::LoneSurrogate, offset)), "{}", input);
+            let borrowed = parse_borrowed_with(input, &ParseOptions::strict()).map(BorrowedValue::into_owned);
+            assert_eq!(outcome(borrowed), outcome(parse_with(input, &ParseOptions::strict())));
+        }
+    }
+}
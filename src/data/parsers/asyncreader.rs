@@ -0,0 +1,157 @@
+use std::io::{self, ErrorKind};
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::super::{DataError, DataErrorKind};
+use super::lexer::{Halt, Lexer};
+use super::source::Source;
+
+const CHUNK_SIZE: usize = 8192;
+
+/// Input handed over in chunks as it arrives, for parsing without blocking. `fill` only releases
+/// input: the lexer reports `Halt::Incomplete` instead of waiting, and the caller pushes more.
+pub struct PushSource {
+    buf: Vec<u8>,
+    base: usize,
+    ended: bool,
+    limited: bool,
+    max_input: Option<usize>
+}
+
+impl PushSource {
+    pub fn new(max_input: Option<usize>) -> Self {
+        Self { buf: Vec::new(), base: 0, ended: false, limited: false, max_input }
+    }
+
+    /// Appends the next chunk of input. Input beyond the size limit is dropped and marks the window
+    /// `limited`.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let room = match self.max_input {
+            Some(max) => max - self.base - self.buf.len(),
+            None => bytes.len()
+        };
+        if bytes.len() > room {
+            self.limited = true;
+        }
+        self.buf.extend_from_slice(&bytes[..bytes.len().min(room)]);
+    }
+
+    /// Marks the end of the input.
+    pub fn end(&mut self) {
+        self.ended = true;
+    }
+}
+
+impl Source for PushSource {
+    fn window(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn base(&self) -> usize {
+        self.base
+    }
+
+    fn complete(&self) -> bool {
+        self.ended
+    }
+
+    fn limited(&self) -> bool {
+        self.limited
+    }
+
+    fn fill(&mut self, keep: usize) -> io::Result<()> {
+        self.buf.drain(..keep);
+        self.base += keep;
+        return Ok(());
+    }
+}
+
+/// Lexer over the bytes of an `AsyncRead`, which is read on the calling task whenever a step runs
+/// out of input. Nothing blocks: while `r` has nothing to give, the task waits on it like any other.
+pub struct AsyncLexer<R: AsyncRead + Unpin> {
+    r: R,
+    lexer: Lexer<PushSource>,
+    chunk: Vec<u8>
+}
+
+impl<R: AsyncRead + Unpin> AsyncLexer<R> {
+    pub fn new(r: R, lexer: Lexer<PushSource>) -> Self {
+        Self { r, lexer, chunk: Vec::new() }
+    }
+
+    /// Takes `step` to completion, reading more input each time it returns `Halt::Incomplete`.
+    pub async fn drive<T>(&mut self, mut step: impl FnMut(&mut Lexer<PushSource>) -> Result<T, Halt>) -> Result<T, DataError> {
+        loop {
+            match step(&mut self.lexer) {
+                Ok(v) => return Ok(v),
+                Err(Halt::Error(e)) => return Err(e),
+                Err(Halt::Incomplete) => self.read().await?
+            }
+        }
+    }
+
+    /// Releases the input already read and appends the next chunk of `r`. Chunks are at least as
+    /// large as the token being read, so a long token takes a number of reads logarithmic in its
+    /// length rather than linear, each of which scans it again from its start.
+    async fn read(&mut self) -> Result<(), DataError> {
+        self.lexer.refill()?;
+        let size = CHUNK_SIZE.max(self.lexer.source_mut().window().len());
+        self.chunk.resize(size, 0);
+        loop {
+            match self.r.read(&mut self.chunk).await {
+                Ok(0) => self.lexer.source_mut().end(),
+                Ok(n) => self.lexer.source_mut().push(&self.chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(DataError::new(DataErrorKind::Io, &format!("Read error: {}", e), self.lexer.current_position()))
+            }
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::super::super::DataErrorKind;
+    use super::super::{parse_async, parse_async_with, parse_map_async, parse_map_with, parse_with, ParseOptions};
+
+    const INPUT: &str = "{\"name\": \"caf\u{e9} \u{1f600}\", \"list\": [1, 2.5, true, null], \"when\": \"2024-08-25T12:15:28Z\",\n \"long\": \"abcdefghijklmnopqrstuvwxyz0123456789\"}";
+
+    /// Writes `input` a few bytes at a time, yielding between writes so that the parser, on the same
+    /// single threaded runtime, has to stop and wait for each chunk. Stops once the parser does.
+    async fn send(mut w: tokio::io::DuplexStream, input: &[u8], size: usize) {
+        for chunk in input.chunks(size) {
+            if w.write_all(chunk).await.is_err() {
+                return;
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn parses_input_split_across_chunks() {
+        let expected = parse_with(INPUT, &ParseOptions::default()).unwrap();
+        for size in [1, 2, 3, 7, 64] {
+            let (w, r) = tokio::io::duplex(size);
+            let (value, _) = tokio::join!(parse_async(r), send(w, INPUT.as_bytes(), size));
+            assert_eq!(value.unwrap(), expected, "chunks of {}", size);
+            let (w, r) = tokio::io::duplex(size);
+            let (map, _) = tokio::join!(parse_map_async(r), send(w, INPUT.as_bytes(), size));
+            assert_eq!(map.unwrap(), parse_map_with(INPUT, &ParseOptions::default()).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_match_those_of_input_held_in_memory() {
+        let options = ParseOptions::strict();
+        for input in ["{\"a\": [1, 2,, 3]}", "[\"\u{e9}\u{e9}\" x]", "{\"a\": 1} 2", "[1, 2"] {
+            let expected = parse_with(input, &options).unwrap_err();
+            let (w, r) = tokio::io::duplex(2);
+            let (res, _) = tokio::join!(parse_async_with(r, &options), send(w, input.as_bytes(), 2));
+            let e = res.unwrap_err();
+            assert_ne!(e.kind, DataErrorKind::Io);
+            assert_eq!((e.kind, e.position.offset, e.position.column), (expected.kind, expected.position.offset, expected.position.column), "{}", input);
+        }
+    }
+}
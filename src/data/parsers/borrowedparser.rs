@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use super::super::orderedmap::OrderedMap;
+use super::super::{BorrowedValue, DataError, DataErrorKind, DataValue};
+use super::lexer::{Halt, Lexer, Text, Token};
+use super::source::SliceSource;
+use super::{literal_value, DuplicateKeyPolicy, ParseOptions};
+
+enum Partial<'a> {
+    Map {
+        map: OrderedMap<Cow<'a, str>, BorrowedValue<'a>>,
+        key: Cow<'a, str>,
+        collected: HashSet<Cow<'a, str>>
+    },
+    List(Vec<BorrowedValue<'a>>)
+}
+
+/// Builds a `BorrowedValue` from the tokens of an input held in memory, slicing strings and keys
+/// out of the input rather than copying them, unless they have escape sequences. The grammar is
+/// the `Lexer`'s, so the syntax accepted is exactly that of `EventReader` under the same options.
+pub struct BorrowedParser<'a> {
+    input: &'a str,
+    lexer: Lexer<SliceSource<'a>>,
+    stack: Vec<Partial<'a>>
+}
+
+impl<'a> BorrowedParser<'a> {
+    pub fn new(input: &'a str, options: &ParseOptions) -> Self {
+        let lexer = Lexer::new(SliceSource::new(input.as_bytes(), options.limits.max_input_size), options);
+        Self { input, lexer, stack: Vec::new() }
+    }
+
+    pub fn parse(mut self) -> Result<BorrowedValue<'a>, DataError> {
+        loop {
+            let token = match self.lexer.poll_token() {
+                Ok(Some(token)) => token,
+                Ok(None) => return Err(self.halted(Halt::Incomplete)),
+                Err(halt) => return Err(self.halted(halt))
+            };
+            let value = match token {
+                Token::StartMap => {
+                    self.stack.push(Partial::Map { map: OrderedMap::new(), key: Cow::Borrowed(""), collected: HashSet::new() });
+                    continue;
+                },
+                Token::StartList => {
+                    self.stack.push(Partial::List(Vec::new()));
+                    continue;
+                },
+                Token::Key(text) => {
+                    let text = borrow(self.input, text);
+                    self.set_key(text)?;
+                    continue;
+                },
+                Token::EndMap | Token::EndList => match self.stack.pop() {
+                    Some(Partial::Map { map, .. }) => BorrowedValue::Map(map),
+                    Some(Partial::List(list)) => BorrowedValue::List(list),
+                    None => BorrowedValue::Null
+                },
+                Token::Literal(text, quoted) => {
+                    let text = borrow(self.input, text);
+                    self.literal(text, quoted)?
+                }
+            };
+            if self.stack.is_empty() {
+                if !self.lexer.options().allow_trailing_content {
+                    self.lexer.finish()?;
+                }
+                return Ok(value);
+            }
+            self.insert(value);
+        }
+    }
+
+    /// The error behind `halt`. Input held in memory never runs out part way, only at its end.
+    fn halted(&self, halt: Halt) -> DataError {
+        match halt {
+            Halt::Error(e) => return e,
+            Halt::Incomplete => return DataError::new(DataErrorKind::UnexpectedEnd, "Unexpected end of input", self.lexer.current_position())
+        }
+    }
+
+    fn set_key(&mut self, text: Cow<'a, str>) -> Result<(), DataError> {
+        let duplicate_keys = self.lexer.options().duplicate_keys;
+        if let Some(Partial::Map { map, key, .. }) = self.stack.last_mut() {
+            let duplicate = duplicate_keys == DuplicateKeyPolicy::Error && map.contains_key(&text);
+            *key = text;
+            if duplicate {
+                let message = format!("Duplicate key '{}'", key);
+                return Err(DataError::new(DataErrorKind::DuplicateKey, &message, self.lexer.event_position()).with_path(self.lexer.path()));
+            }
+        }
+        return Ok(());
+    }
+
+    fn literal(&self, text: Cow<'a, str>, quoted: bool) -> Result<BorrowedValue<'a>, DataError> {
+        let lexer = &self.lexer;
+        match literal_value(&text, quoted, lexer.event_position(), lexer.options(), lexer.date_detection()) {
+            Ok(Some(DataValue::Number(n))) => return Ok(BorrowedValue::Number(n)),
+            Ok(Some(DataValue::Bool(b))) => return Ok(BorrowedValue::Bool(b)),
+            Ok(Some(DataValue::Date(d))) => return Ok(BorrowedValue::Date(d)),
+            Ok(Some(_)) => return Ok(BorrowedValue::Null),
+            Ok(None) => return Ok(BorrowedValue::String(text)),
+            Err(e) => return Err(e.with_path(lexer.path()))
+        }
+    }
+
+    fn insert(&mut self, value: BorrowedValue<'a>) {
+        let duplicate_keys = self.lexer.options().duplicate_keys;
+        match self.stack.last_mut() {
+            Some(Partial::List(list)) => list.push(value),
+            Some(Partial::Map { map, key, collected }) => match map.get_mut(key) {
+                Some(existing) => match duplicate_keys {
+                    DuplicateKeyPolicy::FirstWins => (),
+                    DuplicateKeyPolicy::Collect => {
+                        if collected.contains(key) {
+                            if let BorrowedValue::List(list) = existing {
+                                list.push(value);
+                            }
+                        } else {
+                            let first = std::mem::replace(existing, BorrowedValue::Null);
+                            *existing = BorrowedValue::List(vec![first, value]);
+                            collected.insert(key.clone());
+                        }
+                    },
+                    _ => *existing = value
+                },
+                None => {
+                    map.insert(key.clone(), value);
+                }
+            },
+            None => ()
+        }
+    }
+}
+
+/// Slices `text` out of the input when it is there as is, otherwise takes a copy.
+fn borrow<'a>(input: &'a str, text: Text<'_>) -> Cow<'a, str> {
+    match text.offset {
+        Some(offset) => return Cow::Borrowed(&input[offset..offset + text.text.len()]),
+        None => return Cow::Owned(text.text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::DataValue;
+    use super::super::{parse_borrowed_with, parse_reader_with, ParseOptions};
+
+    #[test]
+    fn comment_after_unquoted_key_is_not_part_of_key() {
+        let options = ParseOptions::json5();
+        for input in ["{abc/*x*/:1}", "{abc// c\n:1}", "{abc /*x*/ :1}"] {
+            let borrowed = parse_borrowed_with(input, &options).unwrap();
+            assert!(borrowed.get("abc").is_some(), "{}", input);
+            let streamed = parse_reader_with(input.as_bytes(), &options).unwrap();
+            match streamed {
+                DataValue::DataMap(map) => assert!(map.has("abc"), "{}", input),
+                _ => panic!("{}", input)
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::Read;
+
+use super::super::{DataError, DataValue};
+use super::EventReader;
+
+/// Iterator over successive top level values in a stream, separated by whitespace or nothing at
+/// all. Each item carries the byte offset of the first character of the value. Iteration stops
+/// after the first error, as the stream cannot be resynchronised.
+pub struct Documents<R: Read> {
+    events: EventReader<R>,
+    done: bool
+}
+
+impl<R: Read> Documents<R> {
+    pub fn new(events: EventReader<R>) -> Self {
+        Self { events, done: false }
+    }
+
+    fn read_document(&mut self) -> Result<Option<(usize, DataValue)>, DataError> {
+        if !self.events.next_document()? {
+            return Ok(None);
+        }
+        let offset = self.events.current_position().offset;
+        let value = self.events.read_value()?;
+        return Ok(Some((offset, value)));
+    }
+}
+
+impl<R: Read> Iterator for Documents<R> {
+    type Item = Result<(usize, DataValue), DataError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_document() {
+            Ok(Some(document)) => return Some(Ok(document)),
+            Ok(None) => {
+                self.done = true;
+                return None;
+            },
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::serializers::{serialize_with, SerializeOptions};
+    use super::super::super::{DataError, DataErrorKind, DataValue};
+    use super::super::{documents, documents_with, parse_with, ParseOptions};
+
+    fn read(input: &str) -> Vec<Result<(usize, String), DataErrorKind>> {
+        let text = |v: &DataValue| serialize_with(v, &SerializeOptions::compact());
+        return documents(input.as_bytes()).map(|d| d.map(|(offset, v)| (offset, text(&v))).map_err(|e: DataError| e.kind)).collect();
+    }
+
+    fn ok(documents: &[(usize, &str)]) -> Vec<Result<(usize, String), DataErrorKind>> {
+        return documents.iter().map(|(offset, text)| Ok((*offset, text.to_string()))).collect();
+    }
+
+    #[test]
+    fn newline_delimited_documents() {
+        let input = "{\"a\": 1}\n{\"a\": 2}\n\n[3, \"x\"]\n\"s\"\n";
+        assert_eq!(read(input), ok(&[(0, "{\"a\":1}"), (9, "{\"a\":2}"), (19, "[3,\"x\"]"), (28, "\"s\"")]));
+        assert_eq!(read("{\"a\": 1}\r\n{\"a\": 2}"), ok(&[(0, "{\"a\":1}"), (10, "{\"a\":2}")]));
+        assert_eq!(read(""), ok(&[]));
+        assert_eq!(read(" \n "), ok(&[]));
+    }
+
+    #[test]
+    fn offsets_of_documents_with_and_without_separators() {
+        assert_eq!(read("1 2 [3]{\"b\": 4}"), ok(&[(0, "1"), (2, "2"), (4, "[3]"), (7, "{\"b\":4}")]));
+        assert_eq!(read("[1][2]{}\"x\"{}"), ok(&[(0, "[1]"), (3, "[2]"), (6, "{}"), (8, "\"x\""), (11, "{}")]));
+        // Offsets are in bytes, not characters.
+        assert_eq!(read("\"\u{e9}\" 1"), ok(&[(0, "\"\u{e9}\""), (5, "1")]));
+    }
+
+    #[test]
+    fn an_error_ends_iteration() {
+        let documents = read("[1] {\"a\" 2} [3]");
+        assert_eq!(documents, vec![Ok((0, "[1]".to_string())), Err(DataErrorKind::ExpectedColon)]);
+        assert_eq!(read("[1] [2"), vec![Ok((0, "[1]".to_string())), Err(DataErrorKind::UnexpectedEnd)]);
+
+        let mut iter = documents_with("1 ] 2".as_bytes(), &ParseOptions::strict());
+        assert_eq!(iter.next().unwrap().unwrap().0, 0);
+        let e = iter.next().unwrap().unwrap_err();
+        assert_eq!((e.kind, e.position.offset), (DataErrorKind::UnexpectedCharacter, 2));
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn strict_parse_rejects_what_documents_reads() {
+        let input = "[1] [2]";
+        assert_eq!(read(input), ok(&[(0, "[1]"), (4, "[2]")]));
+        let e = parse_with(input, &ParseOptions::strict()).unwrap_err();
+        assert_eq!((e.kind, e.position.offset), (DataErrorKind::TrailingContent, 4));
+        let lenient = ParseOptions { allow_trailing_content: true, ..ParseOptions::strict() };
+        assert_eq!(serialize_with(&parse_with(input, &lenient).unwrap(), &SerializeOptions::compact()), "[1]");
+    }
+}
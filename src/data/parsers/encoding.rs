@@ -0,0 +1,217 @@
+use super::super::{DataError, DataErrorKind, Position};
+
+/// Encodings accepted by `parse_bytes`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be
+}
+
+impl Encoding {
+    /// Size in bytes of a code unit.
+    fn unit(&self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4
+        }
+    }
+}
+
+/// Detects the encoding of `bytes` from its byte order mark, or failing that from the pattern
+/// of zero bytes at its start as described in RFC 4627: the first two characters of a document
+/// are ASCII, so their zero high bytes give the encoding away. Returns the encoding together with
+/// the length of the byte order mark, which is zero when there is none.
+pub fn detect_encoding(bytes: &[u8]) -> (Encoding, usize) {
+    match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => return (Encoding::Utf8, 3),
+        [0xFF, 0xFE, 0, 0, ..] => return (Encoding::Utf32Le, 4),
+        [0, 0, 0xFE, 0xFF, ..] => return (Encoding::Utf32Be, 4),
+        [0xFF, 0xFE, ..] => return (Encoding::Utf16Le, 2),
+        [0xFE, 0xFF, ..] => return (Encoding::Utf16Be, 2),
+        [0, 0, 0, _, ..] => return (Encoding::Utf32Be, 0),
+        [_, 0, 0, 0, ..] => return (Encoding::Utf32Le, 0),
+        [0, _, ..] => return (Encoding::Utf16Be, 0),
+        [_, 0, ..] => return (Encoding::Utf16Le, 0),
+        _ => return (Encoding::Utf8, 0)
+    }
+}
+
+/// Decodes UTF-16 or UTF-32 input, starting after a byte order mark of `bom` bytes. Stops once
+/// the text is longer than `max` bytes, leaving the parser to report the input as too large.
+/// Fails with `InvalidUtf16` or `InvalidUtf32` at the first code unit that does not decode.
+pub fn decode(bytes: &[u8], encoding: Encoding, bom: usize, max: Option<usize>) -> Result<String, DataError> {
+    let unit = encoding.unit();
+    let mut text = String::with_capacity((bytes.len() - bom) / unit);
+    let mut position = Position { offset: bom, line: 1, column: 1 };
+    let mut units = bytes[bom..].chunks(unit);
+    let (kind, name) = match unit {
+        2 => (DataErrorKind::InvalidUtf16, "UTF-16"),
+        _ => (DataErrorKind::InvalidUtf32, "UTF-32")
+    };
+    let error = |message: &str, position: Position| DataError::new(kind, &format!("{} {}", message, name), position);
+    while let Some(first) = units.next() {
+        if max.is_some_and(|max| text.len() > max) {
+            break;
+        }
+        if first.len() < unit {
+            return Err(error("Truncated", position));
+        }
+        let c = match encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let read = |u: &[u8]| if encoding == Encoding::Utf16Le { u16::from_le_bytes([u[0], u[1]]) } else { u16::from_be_bytes([u[0], u[1]]) };
+                let high = read(first);
+                match high {
+                    0xD800..=0xDBFF => match units.next() {
+                        Some(second) if second.len() == 2 && (0xDC00..0xE000).contains(&read(second)) => {
+                            char::from_u32(0x10000 + ((high as u32 - 0xD800) << 10) + (read(second) as u32 - 0xDC00))
+                        },
+                        _ => None
+                    },
+                    _ => char::from_u32(high as u32)
+                }
+            },
+            _ => {
+                let u = [first[0], first[1], first[2], first[3]];
+                char::from_u32(if encoding == Encoding::Utf32Le { u32::from_le_bytes(u) } else { u32::from_be_bytes(u) })
+            }
+        };
+        let c = match c {
+            Some(c) => c,
+            None => return Err(error("Invalid", position))
+        };
+        text.push(c);
+        position.offset += if unit == 2 { 2 * c.len_utf16() } else { 4 };
+        position.column += 1;
+        if c == '\n' {
+            position.column = 1;
+            position.line += 1;
+        }
+    }
+    return Ok(text);
+}
+
+/// Translates a byte offset into `text`, decoded from input in `encoding` after a byte order mark
+/// of `bom` bytes, back into a byte offset into the input.
+pub fn input_offset(text: &str, offset: usize, encoding: Encoding, bom: usize) -> usize {
+    let prefix = text.get(..offset).unwrap_or(text);
+    match encoding {
+        Encoding::Utf8 => return bom + offset,
+        Encoding::Utf16Le | Encoding::Utf16Be => return bom + 2 * prefix.encode_utf16().count(),
+        Encoding::Utf32Le | Encoding::Utf32Be => return bom + 4 * prefix.chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::DataErrorKind;
+    use super::super::{parse_bytes, parse_with, ParseOptions};
+    use super::{detect_encoding, Encoding};
+
+    const TEXT: &str = "{\"name\": \"caf\u{e9} \u{1f600}\",\n \"list\": [1, \"\u{10ffff}\"]}";
+
+    const ALL: [Encoding; 4] = [Encoding::Utf16Le, Encoding::Utf16Be, Encoding::Utf32Le, Encoding::Utf32Be];
+
+    fn bom(encoding: Encoding) -> &'static [u8] {
+        match encoding {
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Utf32Le => &[0xFF, 0xFE, 0, 0],
+            Encoding::Utf32Be => &[0, 0, 0xFE, 0xFF]
+        }
+    }
+
+    fn encode(text: &str, encoding: Encoding, with_bom: bool) -> Vec<u8> {
+        let mut bytes = if with_bom { bom(encoding).to_vec() } else { Vec::new() };
+        match encoding {
+            Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => text.encode_utf16().for_each(|u| bytes.extend_from_slice(&u.to_le_bytes())),
+            Encoding::Utf16Be => text.encode_utf16().for_each(|u| bytes.extend_from_slice(&u.to_be_bytes())),
+            Encoding::Utf32Le => text.chars().for_each(|c| bytes.extend_from_slice(&(c as u32).to_le_bytes())),
+            Encoding::Utf32Be => text.chars().for_each(|c| bytes.extend_from_slice(&(c as u32).to_be_bytes()))
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn detects_encodings_with_and_without_bom() {
+        for encoding in [Encoding::Utf8].into_iter().chain(ALL) {
+            assert_eq!(detect_encoding(&encode(TEXT, encoding, true)), (encoding, bom(encoding).len()));
+            assert_eq!(detect_encoding(&encode(TEXT, encoding, false)), (encoding, 0));
+        }
+        assert_eq!(detect_encoding(b""), (Encoding::Utf8, 0));
+        assert_eq!(detect_encoding(b"1"), (Encoding::Utf8, 0));
+    }
+
+    #[test]
+    fn parses_every_encoding_with_and_without_bom() {
+        let expected = parse_with(TEXT, &ParseOptions::default()).unwrap();
+        for encoding in [Encoding::Utf8].into_iter().chain(ALL) {
+            for with_bom in [true, false] {
+                let value = parse_bytes(&encode(TEXT, encoding, with_bom));
+                assert_eq!(value.ok().as_ref(), Some(&expected), "{:?}, bom {}", encoding, with_bom);
+            }
+        }
+    }
+
+    #[test]
+    fn unpaired_surrogates_are_rejected_at_their_offset() {
+        // A high surrogate followed by a character that is not a low surrogate.
+        let mut bytes = encode("[\"a", Encoding::Utf16Le, true);
+        bytes.extend_from_slice(&[0x3D, 0xD8, 0x41, 0x00]);
+        let e = parse_bytes(&bytes).unwrap_err();
+        assert_eq!((e.kind, e.position.offset, e.position.column), (DataErrorKind::InvalidUtf16, 8, 4));
+        // A low surrogate on its own, and a high surrogate at the end of the input.
+        let mut bytes = encode("[\"", Encoding::Utf16Be, false);
+        bytes.extend_from_slice(&[0xDC, 0x00]);
+        assert_eq!(parse_bytes(&bytes).unwrap_err().position.offset, 4);
+        let mut bytes = encode("[\"", Encoding::Utf16Be, false);
+        bytes.extend_from_slice(&[0xD8, 0x3D]);
+        assert_eq!(parse_bytes(&bytes).unwrap_err().kind, DataErrorKind::InvalidUtf16);
+        // UTF-32 has no surrogates, and nothing above U+10FFFF.
+        for unit in [0xD800u32, 0x110000] {
+            let mut bytes = encode("[\"", Encoding::Utf32Le, false);
+            bytes.extend_from_slice(&unit.to_le_bytes());
+            let e = parse_bytes(&bytes).unwrap_err();
+            assert_eq!((e.kind, e.position.offset), (DataErrorKind::InvalidUtf32, 8));
+        }
+    }
+
+    #[test]
+    fn truncated_code_units_are_rejected() {
+        for (encoding, kind, unit) in [
+            (Encoding::Utf16Le, DataErrorKind::InvalidUtf16, 2),
+            (Encoding::Utf16Be, DataErrorKind::InvalidUtf16, 2),
+            (Encoding::Utf32Le, DataErrorKind::InvalidUtf32, 4),
+            (Encoding::Utf32Be, DataErrorKind::InvalidUtf32, 4)
+        ] {
+            for with_bom in [true, false] {
+                let mut bytes = encode("[\"\n\"]", encoding, with_bom);
+                bytes.pop();
+                let e = parse_bytes(&bytes).unwrap_err();
+                let last = bytes.len() + 1 - unit;
+                assert_eq!((e.kind, e.position.offset, e.position.line, e.position.column), (kind, last, 2, 2), "{:?}", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn error_offsets_are_into_the_input_bytes() {
+        let text = "[\"\u{1f600}\u{e9}\" x]";
+        let expected = parse_with(text, &ParseOptions::default()).unwrap_err();
+        assert_eq!(expected.position.offset, 10);
+        // Before `x` come 7 UTF-16 code units, the emoji taking two, and 6 characters.
+        for (encoding, offset) in [(Encoding::Utf8, 10), (Encoding::Utf16Le, 14), (Encoding::Utf16Be, 14), (Encoding::Utf32Le, 24), (Encoding::Utf32Be, 24)] {
+            for with_bom in [true, false] {
+                let bytes = encode(text, encoding, with_bom);
+                let e = parse_bytes(&bytes).unwrap_err();
+                let offset = offset + if with_bom { bom(encoding).len() } else { 0 };
+                assert_eq!((e.kind, e.position.offset, e.position.column), (expected.kind, offset, expected.position.column), "{:?}, bom {}", encoding, with_bom);
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::io::Read;
+
+use super::super::{DataError, DataPath, DataValue, Position};
+use super::lexer::Lexer;
+use super::source::ReadSource;
+use super::{parse_from_reader, ParseOptions};
+
+/// A single step of a parse, in document order. Literal values (strings, numbers, booleans,
+/// dates and null) are delivered whole, containers are delivered as matching start and end events.
+pub enum Event {
+    StartMap,
+    Key(String),
+    EndMap,
+    StartList,
+    EndList,
+    Value(DataValue)
+}
+
+/// Pull parser that yields one `Event` at a time without building a `DataValue` tree.
+pub struct EventReader<R: Read> {
+    lexer: Lexer<ReadSource<R>>
+}
+
+#[allow(dead_code)]
+impl<R: Read> EventReader<R> {
+    pub fn new(r: R) -> Self {
+        Self::with_options(r, &ParseOptions::default())
+    }
+
+    pub fn with_options(r: R, options: &ParseOptions) -> Self {
+        Self { lexer: Lexer::new(ReadSource::new(r, options.limits.max_input_size), options) }
+    }
+
+    /// Returns the next event, or `None` once the top level value has been completely read.
+    pub fn next_event(&mut self) -> Result<Option<Event>, DataError> {
+        return self.lexer.next_event();
+    }
+
+    /// Reads the next complete value from the stream, materialising it as a `DataValue`. Used after
+    /// a `Key` event, or at the start of the stream, to extract only the parts of a document of interest.
+    pub fn read_value(&mut self) -> Result<DataValue, DataError> {
+        return parse_from_reader(&mut self.lexer);
+    }
+
+    /// Skips the whitespace and comments that follow a complete top level value and prepares to
+    /// read another one from the same stream. Returns false at the end of the input.
+    pub fn next_document(&mut self) -> Result<bool, DataError> {
+        return self.lexer.next_document();
+    }
+
+    /// Checks that nothing but whitespace and comments follows the value just read.
+    pub fn finish(&mut self) -> Result<(), DataError> {
+        return self.lexer.finish();
+    }
+
+    /// Skips past the input that caused `error` to the next `,`, `}` or `]` of the container being
+    /// read, so that reading can continue after it. Nested containers and quoted strings are skipped
+    /// whole. Returns false when the error cannot be recovered from, e.g. at the end of the input
+    /// or outside any container.
+    pub fn recover(&mut self, error: &DataError) -> bool {
+        return self.lexer.recover(error);
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        self.lexer.options()
+    }
+
+    /// Nesting depth of the container currently being read.
+    pub fn depth(&self) -> usize {
+        self.lexer.depth()
+    }
+
+    /// Position of the next character to be read.
+    pub fn current_position(&self) -> Position {
+        self.lexer.current_position()
+    }
+
+    /// Position of the first character of the most recently returned event.
+    pub fn event_position(&self) -> Position {
+        self.lexer.event_position()
+    }
+
+    /// Position just past the last character of the most recently returned event. For `StartMap`
+    /// and `StartList` this is just past the opening bracket.
+    pub fn event_end(&self) -> Position {
+        self.lexer.event_end()
+    }
+
+    /// Path from the root to the value currently being read.
+    pub fn path(&self) -> DataPath {
+        self.lexer.path()
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = Result<Event, DataError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Ok(Some(event)) => return Some(Ok(event)),
+            Ok(None) => return None,
+            Err(e) => {
+                self.lexer.abandon();
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::serializers::{serialize_with, SerializeOptions};
+    use super::super::{parse_recovering_with, ParseOptions};
+
+    fn recover(input: &str, options: &ParseOptions) -> (String, usize) {
+        let (value, errors) = parse_recovering_with(input, options);
+        return (serialize_with(&value, &SerializeOptions::compact()), errors.len());
+    }
+
+    #[test]
+    fn recovery_resumes_after_missing_comma_before_string() {
+        let strict = ParseOptions::strict();
+        assert_eq!(recover("{\"a\": 1 \"b\": 2, \"c\": 3}", &strict), ("{\"a\":1,\"c\":3}".to_string(), 1));
+        assert_eq!(recover("[\"x\" \"y\", \"z\", \"w\"]", &strict), ("[\"x\",\"z\",\"w\"]".to_string(), 1));
+        assert_eq!(recover("{'a': 1 'b': 2, 'c': 3}", &ParseOptions::json5()), ("{\"a\":1,\"c\":3}".to_string(), 1));
+    }
+
+    #[test]
+    fn recovery_keeps_the_entry_after_a_missing_value_or_key() {
+        for options in [ParseOptions::strict(), ParseOptions::default()] {
+            assert_eq!(recover("{\"a\": , \"b\": 1, \"c\": 2}", &options), ("{\"b\":1,\"c\":2}".to_string(), 1));
+            assert_eq!(recover("[1, , 2, 3]", &options), ("[1,2,3]".to_string(), 1));
+            assert_eq!(recover("{\"a\": 1, , \"b\": 2}", &options), ("{\"a\":1,\"b\":2}".to_string(), 1));
+            assert_eq!(recover("[1, 2, ]", &options), ("[1,2]".to_string(), 1));
+            assert_eq!(recover("{\"a\": 1, }", &options), ("{\"a\":1}".to_string(), 1));
+            assert_eq!(recover("{\"a\": [1, ], \"b\": }", &options), ("{\"a\":[1]}".to_string(), 2));
+        }
+    }
+
+    #[test]
+    fn recovery_skips_nested_containers() {
+        let strict = ParseOptions::strict();
+        assert_eq!(recover("[1, {\"a\": x, \"b\": [1, 2]}, 3]", &strict), ("[1,{\"b\":[1,2]},3]".to_string(), 1));
+        assert_eq!(recover("[1 [2, 3], 4]", &strict), ("[1,4]".to_string(), 1));
+    }
+}
@@ -0,0 +1,1094 @@
+use memchr::{memchr, memchr2, memchr_iter, memmem, memrchr};
+
+use super::super::{DataError, DataErrorKind, DataPath, DataValue, Position};
+use super::source::Source;
+use super::{literal_value, DateDetection, Event, ParseOptions};
+
+/// Why a step of the lexer stopped short of a result.
+pub enum Halt {
+    Error(DataError),
+    /// The window ends inside the token being read. The step has been undone, and can be taken
+    /// again once more input has been made available.
+    Incomplete
+}
+
+impl From<DataError> for Halt {
+    fn from(e: DataError) -> Self {
+        Halt::Error(e)
+    }
+}
+
+/// Text of a key or literal. `offset` is where it starts in the input, when it appears there as is,
+/// without escape sequences.
+pub struct Text<'t> {
+    pub text: &'t str,
+    pub offset: Option<usize>
+}
+
+/// A single token of the input, borrowing its text from the lexer.
+pub enum Token<'t> {
+    StartMap,
+    EndMap,
+    StartList,
+    EndList,
+    Key(Text<'t>),
+    Literal(Text<'t>, bool)
+}
+
+/// Where the text of the token just read is held.
+#[derive(Clone, Copy)]
+enum Held {
+    /// Between two indexes of the window.
+    Window(usize, usize),
+    /// Unescaped into `scratch`.
+    Scratch
+}
+
+enum Raw {
+    StartMap,
+    EndMap,
+    StartList,
+    EndList,
+    Key(Option<usize>),
+    Literal(Held, bool)
+}
+
+#[derive(Clone, Copy)]
+enum MapState {  BeforeKey, AfterKey, BeforeValue, AfterValue }
+
+#[derive(Clone, Copy)]
+enum ListState {  BeforeValue, AfterValue }
+
+#[derive(Clone, Copy)]
+enum Container {
+    Map(MapState),
+    List(ListState)
+}
+
+struct Frame {
+    container: Container,
+    count: usize,
+    /// Whether `key` holds the key of the entry being read.
+    keyed: bool,
+    key: String
+}
+
+/// State to return to when a step runs out of input.
+#[derive(Clone, Copy)]
+struct Snapshot {
+    pos: usize,
+    line: usize,
+    anchor: usize,
+    column: usize,
+    started: bool,
+    top: Option<(Container, usize, bool)>,
+    start: Position,
+    end: Position
+}
+
+/// How far a string was scanned, free of escapes, before the window ran out, so that scanning can
+/// resume there rather than at its start once more input has arrived.
+#[derive(Clone, Copy, Default)]
+struct Resume {
+    from: usize,
+    to: usize,
+    line: usize,
+    anchor: usize,
+    column: usize
+}
+
+/// Tokenizer shared by every parser, working on the bytes of a `Source` in place. Whitespace is
+/// skipped and string content found with `memchr`, and text is validated as UTF-8 a token at a
+/// time rather than decoded character by character. Keys and literals without escape sequences
+/// are handed out as slices of the window.
+///
+/// Each step reads one token, so when the window runs out part way through one, the step is undone
+/// and `Halt::Incomplete` returned. Blocking callers then `refill` and retry; callers pushing input
+/// retry once more has been pushed.
+///
+/// The methods on the path of every token are forced inline into `poll_event` and `poll_token`,
+/// which is worth about a fifth of the parser's throughput.
+pub struct Lexer<S: Source> {
+    source: S,
+    options: ParseOptions,
+    /// Open containers are `frames[..depth]`. Frames beyond it are kept to reuse their key buffers.
+    frames: Vec<Frame>,
+    depth: usize,
+    started: bool,
+    /// Window index of the next byte to be read.
+    pos: usize,
+    line: usize,
+    /// Input offset at which the column was last taken, on the current line.
+    anchor: usize,
+    column: usize,
+    start: Position,
+    end: Position,
+    scratch: String,
+    resume: Resume
+}
+
+#[allow(dead_code)]
+impl<S: Source> Lexer<S> {
+    pub fn new(source: S, options: &ParseOptions) -> Self {
+        Self {
+            source, options: options.clone(), frames: Vec::new(), depth: 0, started: false, pos: 0, line: 1, anchor: 0, column: 1,
+            start: Position::start(), end: Position::start(), scratch: String::new(), resume: Resume::default()
+        }
+    }
+
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Nesting depth of the container currently being read.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Position of the next byte to be read.
+    pub fn current_position(&self) -> Position {
+        self.position_of(self.pos)
+    }
+
+    /// Position of the first character of the most recently read token.
+    pub fn event_position(&self) -> Position {
+        self.start
+    }
+
+    /// Position just past the last character of the most recently read token.
+    pub fn event_end(&self) -> Position {
+        self.end
+    }
+
+    /// Path from the root to the value currently being read.
+    pub fn path(&self) -> DataPath {
+        let mut path = DataPath::new();
+        for frame in self.frames[..self.depth].iter() {
+            match frame.container {
+                Container::Map(_) => {
+                    if frame.keyed {
+                        path.push_key(&frame.key);
+                    }
+                },
+                Container::List(_) => {
+                    if frame.count > 0 {
+                        path.push_index(frame.count - 1);
+                    }
+                }
+            }
+        }
+        return path;
+    }
+
+    /// Date detection for the value about to be read, turned off when the nearest enclosing key is exempt.
+    pub fn date_detection(&self) -> DateDetection {
+        if !self.options.date_exempt_keys.is_empty() {
+            if let Some(frame) = self.frames[..self.depth].iter().rev().find(|frame| frame.keyed) {
+                if self.options.date_exempt_keys.contains(&frame.key) {
+                    return DateDetection::Off;
+                }
+            }
+        }
+        return self.options.date_detection;
+    }
+
+    /// Reads the next event, or `None` once the top level value has been completely read.
+    pub fn poll_event(&mut self) -> Result<Option<Event>, Halt> {
+        let raw = match self.step(Self::read)? {
+            Some(raw) => raw,
+            None => return Ok(None)
+        };
+        let event = match raw {
+            Raw::StartMap => Event::StartMap,
+            Raw::EndMap => Event::EndMap,
+            Raw::StartList => Event::StartList,
+            Raw::EndList => Event::EndList,
+            Raw::Key(_) => Event::Key(self.frames[self.depth - 1].key.clone()),
+            Raw::Literal(held, quoted) => {
+                let text = self.text(held)?;
+                let value = literal_value(text, quoted, self.start, &self.options, self.date_detection());
+                match value.map_err(|e| e.with_path(self.path()))? {
+                    Some(v) => Event::Value(v),
+                    None => Event::Value(DataValue::String(text.to_string()))
+                }
+            }
+        };
+        return Ok(Some(event));
+    }
+
+    /// Reads the next token, or `None` once the top level value has been completely read.
+    pub fn poll_token(&mut self) -> Result<Option<Token<'_>>, Halt> {
+        let raw = match self.step(Self::read)? {
+            Some(raw) => raw,
+            None => return Ok(None)
+        };
+        let token = match raw {
+            Raw::StartMap => Token::StartMap,
+            Raw::EndMap => Token::EndMap,
+            Raw::StartList => Token::StartList,
+            Raw::EndList => Token::EndList,
+            Raw::Key(offset) => Token::Key(Text { text: &self.frames[self.depth - 1].key, offset }),
+            Raw::Literal(held, quoted) => {
+                let offset = match held {
+                    Held::Window(from, _) => Some(self.source.base() + from),
+                    Held::Scratch => None
+                };
+                Token::Literal(Text { text: self.text(held)?, offset }, quoted)
+            }
+        };
+        return Ok(Some(token));
+    }
+
+    /// Skips the whitespace and comments that follow a complete top level value and prepares to
+    /// read another one. Returns false at the end of the input.
+    pub fn poll_document(&mut self) -> Result<bool, Halt> {
+        return self.step(|lexer| {
+            if !lexer.skip_space()? {
+                return Ok(false);
+            }
+            lexer.depth = 0;
+            lexer.started = false;
+            return Ok(true);
+        });
+    }
+
+    /// Checks that nothing but whitespace and comments follows the value just read.
+    pub fn poll_finish(&mut self) -> Result<(), Halt> {
+        if self.poll_document()? {
+            return Err(self.error(DataErrorKind::TrailingContent, "Unexpected content after the end of the document"));
+        }
+        return Ok(());
+    }
+
+    /// Skips past the input that caused `error` to the next `,`, `}` or `]` of the container being
+    /// read, so that reading can continue after it. Nested containers and quoted strings are skipped
+    /// whole. Returns false when the error cannot be recovered from, e.g. at the end of the input
+    /// or outside any container.
+    pub fn poll_recover(&mut self, error: &DataError) -> Result<bool, Halt> {
+        match error.kind {
+            DataErrorKind::Io | DataErrorKind::InvalidUtf8 | DataErrorKind::InputTooLarge | DataErrorKind::UnexpectedEnd => return Ok(false),
+            _ => ()
+        }
+        if self.depth == 0 {
+            return Ok(false);
+        }
+        return self.step(Self::skip_entry);
+    }
+
+    /// Releases the input before the next byte to be read and makes more available.
+    pub fn refill(&mut self) -> Result<(), DataError> {
+        self.here();
+        let keep = self.pos;
+        self.pos = 0;
+        match self.source.fill(keep) {
+            Ok(()) => return Ok(()),
+            Err(e) => return Err(DataError::new(DataErrorKind::Io, &format!("Read error: {}", e), self.current_position()))
+        }
+    }
+
+    /// Takes `step` to completion over a source that blocks for more input.
+    pub fn block<T>(&mut self, mut step: impl FnMut(&mut Self) -> Result<T, Halt>) -> Result<T, DataError> {
+        loop {
+            match step(self) {
+                Ok(v) => return Ok(v),
+                Err(Halt::Error(e)) => return Err(e),
+                Err(Halt::Incomplete) => self.refill()?
+            }
+        }
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Event>, DataError> {
+        return self.block(Self::poll_event);
+    }
+
+    pub fn next_document(&mut self) -> Result<bool, DataError> {
+        return self.block(Self::poll_document);
+    }
+
+    pub fn finish(&mut self) -> Result<(), DataError> {
+        return self.block(Self::poll_finish);
+    }
+
+    pub fn recover(&mut self, error: &DataError) -> bool {
+        return self.block(|lexer| lexer.poll_recover(error)).unwrap_or(false);
+    }
+
+    /// Gives up on the value being read, so that nothing more is read from it.
+    pub fn abandon(&mut self) {
+        self.depth = 0;
+        self.started = true;
+    }
+
+    /// Runs `f`, undoing it if it runs out of input and adding the path to any error.
+    #[inline(always)]
+    fn step<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, Halt>) -> Result<T, Halt> {
+        // Input that is all there cannot run out part way through a step.
+        let snapshot = match self.source.complete() {
+            true => None,
+            false => Some(self.snapshot())
+        };
+        match f(self) {
+            Ok(v) => return Ok(v),
+            Err(Halt::Incomplete) => {
+                if let Some(snapshot) = snapshot {
+                    self.restore(snapshot);
+                }
+                return Err(Halt::Incomplete);
+            },
+            Err(Halt::Error(e)) => return Err(Halt::Error(e.with_path(self.path())))
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let top = match self.depth {
+            0 => None,
+            n => Some((self.frames[n - 1].container, self.frames[n - 1].count, self.frames[n - 1].keyed))
+        };
+        Snapshot {
+            pos: self.pos, line: self.line, anchor: self.anchor, column: self.column, started: self.started, top,
+            start: self.start, end: self.end
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.pos = snapshot.pos;
+        self.line = snapshot.line;
+        self.anchor = snapshot.anchor;
+        self.column = snapshot.column;
+        self.started = snapshot.started;
+        self.start = snapshot.start;
+        self.end = snapshot.end;
+        if let Some((container, count, keyed)) = snapshot.top {
+            let frame = &mut self.frames[self.depth - 1];
+            frame.container = container;
+            frame.count = count;
+            frame.keyed = keyed;
+        }
+    }
+
+    #[inline(always)]
+    fn read(&mut self) -> Result<Option<Raw>, Halt> {
+        if self.depth == 0 {
+            if self.started {
+                return Ok(None);
+            }
+            if !self.skip_space()? {
+                return Err(self.error(DataErrorKind::UnexpectedEnd, "Unexpected end of input"));
+            }
+            self.started = true;
+            return Ok(Some(self.start_value()?));
+        }
+        loop {
+            if !self.skip_space()? {
+                return Err(self.error(DataErrorKind::UnexpectedEnd, "Unexpected end of input"));
+            }
+            let c = self.source.window()[self.pos];
+            let top = self.depth - 1;
+            match self.frames[top].container {
+                Container::Map(state) => match state {
+                    MapState::BeforeKey => {
+                        if c == b'}' && (self.frames[top].count == 0 || self.options.allow_trailing_commas) {
+                            return Ok(Some(self.close()));
+                        }
+                        return Ok(Some(self.read_key()?));
+                    },
+                    MapState::AfterKey => {
+                        if c != b':' {
+                            return Err(self.error(DataErrorKind::ExpectedColon, "Expected ':'"));
+                        }
+                        self.pos += 1;
+                        self.frames[top].container = Container::Map(MapState::BeforeValue);
+                    },
+                    MapState::BeforeValue => {
+                        self.frames[top].container = Container::Map(MapState::AfterValue);
+                        return Ok(Some(self.start_value()?));
+                    },
+                    MapState::AfterValue => {
+                        if c == b',' {
+                            self.pos += 1;
+                            self.frames[top].keyed = false;
+                            self.frames[top].container = Container::Map(MapState::BeforeKey);
+                        } else if c == b'}' {
+                            return Ok(Some(self.close()));
+                        } else {
+                            return Err(self.error(DataErrorKind::ExpectedMapSeparator, "Expected ',' or '}'"));
+                        }
+                    }
+                },
+                Container::List(state) => match state {
+                    ListState::BeforeValue => {
+                        if c == b']' && (self.frames[top].count == 0 || self.options.allow_trailing_commas) {
+                            return Ok(Some(self.close()));
+                        }
+                        self.frames[top].container = Container::List(ListState::AfterValue);
+                        self.count_entry(Self::here)?;
+                        return Ok(Some(self.start_value()?));
+                    },
+                    ListState::AfterValue => {
+                        if c == b',' {
+                            self.pos += 1;
+                            self.frames[top].container = Container::List(ListState::BeforeValue);
+                        } else if c == b']' {
+                            return Ok(Some(self.close()));
+                        } else {
+                            return Err(self.error(DataErrorKind::ExpectedListSeparator, "Expected ',' or ']'"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the `}` or `]` at the next byte, closing the innermost container.
+    #[inline(always)]
+    fn close(&mut self) -> Raw {
+        self.start = self.here();
+        self.pos += 1;
+        self.end = after(self.start);
+        self.depth -= 1;
+        match self.frames[self.depth].container {
+            Container::Map(_) => return Raw::EndMap,
+            Container::List(_) => return Raw::EndList
+        }
+    }
+
+    /// Opens a container at `{` and `[`, otherwise reads the literal starting at the next byte.
+    #[inline(always)]
+    fn start_value(&mut self) -> Result<Raw, Halt> {
+        self.start = self.here();
+        let c = self.source.window()[self.pos];
+        match c {
+            b'{' | b'[' => {
+                if let Some(max) = self.options.limits.max_depth {
+                    if self.depth >= max {
+                        return Err(self.error(DataErrorKind::DepthLimitExceeded, &format!("Nesting deeper than {}", max)));
+                    }
+                }
+                self.pos += 1;
+                self.end = after(self.start);
+                let container = match c {
+                    b'{' => Container::Map(MapState::BeforeKey),
+                    _ => Container::List(ListState::BeforeValue)
+                };
+                match self.frames.get_mut(self.depth) {
+                    Some(frame) => {
+                        frame.container = container;
+                        frame.count = 0;
+                        frame.keyed = false;
+                    },
+                    None => self.frames.push(Frame { container, count: 0, keyed: false, key: String::new() })
+                }
+                self.depth += 1;
+                match c {
+                    b'{' => return Ok(Raw::StartMap),
+                    _ => return Ok(Raw::StartList)
+                }
+            },
+            // Left unread, so that recovery resumes at the separator.
+            b'}' | b']' | b',' => return Err(self.error(DataErrorKind::UnexpectedCharacter, &format!("Unexpected '{}'", c as char))),
+            b'"' => (),
+            b'\'' if self.options.allow_single_quotes => (),
+            _ => {
+                let from = self.pos;
+                let to = self.scan_unquoted(false)?;
+                self.check_length(to - from, false)?;
+                self.pos = to;
+                self.end = self.here();
+                return Ok(Raw::Literal(Held::Window(from, to), false));
+            }
+        }
+        self.pos += 1;
+        let held = self.read_quoted(c, false)?;
+        self.end = self.here();
+        return Ok(Raw::Literal(held, true));
+    }
+
+    /// Reads the key starting at the next byte into the key buffer of the innermost frame.
+    #[inline(always)]
+    fn read_key(&mut self) -> Result<Raw, Halt> {
+        self.start = self.here();
+        let c = self.source.window()[self.pos];
+        let offset;
+        if c == b'"' || (c == b'\'' && self.options.allow_single_quotes) {
+            self.pos += 1;
+            let held = self.read_quoted(c, true)?;
+            self.end = self.here();
+            offset = match held {
+                Held::Window(from, _) => Some(self.source.base() + from),
+                Held::Scratch => None
+            };
+            self.set_key(held)?;
+            self.frames[self.depth - 1].container = Container::Map(MapState::AfterKey);
+        } else if c == b',' || c == b'}' || c == b']' {
+            // Left unread, so that recovery resumes at the separator.
+            match self.options.allow_unquoted_keys {
+                true => return Err(self.error(DataErrorKind::UnexpectedCharacter, &format!("Unexpected '{}', expected a key", c as char))),
+                false => return Err(self.error(DataErrorKind::UnquotedKey, "Expected '\"' to start a key"))
+            }
+        } else if !self.options.allow_unquoted_keys {
+            return Err(self.error(DataErrorKind::UnquotedKey, "Expected '\"' to start a key"));
+        } else {
+            let from = self.pos;
+            let to = self.scan_unquoted(true)?;
+            self.check_length(to - from, true)?;
+            self.pos = to;
+            self.end = self.here();
+            offset = Some(self.source.base() + from);
+            self.set_key(Held::Window(from, to))?;
+            let colon = self.source.window().get(to) == Some(&b':');
+            if colon {
+                self.pos += 1;
+            }
+            let state = if colon { MapState::BeforeValue } else { MapState::AfterKey };
+            self.frames[self.depth - 1].container = Container::Map(state);
+        }
+        self.count_entry(|lexer| lexer.start)?;
+        self.frames[self.depth - 1].keyed = true;
+        return Ok(Raw::Key(offset));
+    }
+
+    #[inline(always)]
+    fn set_key(&mut self, held: Held) -> Result<(), Halt> {
+        let frame = &mut self.frames[self.depth - 1];
+        frame.key.clear();
+        match held {
+            Held::Window(from, to) => match std::str::from_utf8(&self.source.window()[from..to]) {
+                Ok(key) => frame.key.push_str(key),
+                Err(e) => return Err(self.invalid_utf8(from + e.valid_up_to()))
+            },
+            Held::Scratch => frame.key.push_str(&self.scratch)
+        }
+        return Ok(());
+    }
+
+    /// Finds the end of the unquoted key or literal starting at the next byte, whose first character
+    /// is taken whatever it is. An unquoted key ends at whitespace, a comment or `:`, and a literal
+    /// at whitespace, a comment, `,`, `}` or `]`. A `"` in a key is an error.
+    #[inline(always)]
+    fn scan_unquoted(&mut self, iskey: bool) -> Result<usize, Halt> {
+        let comments = self.options.allow_comments;
+        let window = self.source.window();
+        let mut i = self.pos + 1;
+        while i < window.len() {
+            match window[i] {
+                b' ' | b'\r' | b'\n' | b'\t' => return Ok(i),
+                b':' if iskey => return Ok(i),
+                b',' | b'}' | b']' if !iskey => return Ok(i),
+                b'"' if iskey => {
+                    self.pos = i + 1;
+                    return Err(Halt::Error(DataError::new(DataErrorKind::QuoteInKey, "Unexpected '\"' in unquoted key", self.position_of(i))));
+                },
+                b'/' if comments => match window.get(i + 1) {
+                    Some(b'/') | Some(b'*') => return Ok(i),
+                    Some(_) => (),
+                    None if self.source.complete() => (),
+                    None => return Err(self.more(i + 1))
+                },
+                _ => ()
+            }
+            i += 1;
+        }
+        self.check_length(i - self.pos, iskey)?;
+        if !self.source.complete() || self.source.limited() {
+            return Err(self.more(i));
+        }
+        if iskey {
+            self.pos = i;
+            return Err(self.error(DataErrorKind::UnexpectedEnd, "Unexpected end of input"));
+        }
+        return Ok(i);
+    }
+
+    /// Reads the rest of a string or key delimited by `quote`, whose opening quote has been read.
+    /// The text stays in the window unless it has escape sequences.
+    #[inline(always)]
+    fn read_quoted(&mut self, quote: u8, iskey: bool) -> Result<Held, Halt> {
+        let base = self.source.base();
+        let from = self.pos;
+        if self.resume.from == base + from && self.resume.to > base + from {
+            self.pos = self.resume.to - base;
+            self.line = self.resume.line;
+            self.anchor = self.resume.anchor;
+            self.column = self.resume.column;
+        }
+        let controls = self.options.allow_control_characters;
+        // Start of the text not yet copied into `scratch`, once an escape sequence has been seen.
+        let mut rest: Option<usize> = None;
+        loop {
+            let window = self.source.window();
+            let stop = self.pos + special(&window[self.pos..], quote);
+            let ended = stop == window.len();
+            let c = window.get(stop).copied();
+            let control = c.filter(|&c| c < b' ' && !controls).map(|_| stop);
+            let copied = |lexer: &Self, to: usize| match rest {
+                Some(at) => lexer.scratch.len() + to - at,
+                None => to - from
+            };
+            if let Some(i) = control {
+                self.check_length(copied(self, i), iskey)?;
+                let position = self.position_of(i);
+                let message = format!("Unescaped control character in {}", if iskey { "key" } else { "string" });
+                self.rewind();
+                return Err(Halt::Error(DataError::new(DataErrorKind::ControlCharacter, &message, position)));
+            }
+            self.check_length(copied(self, stop), iskey)?;
+            if ended {
+                if rest.is_none() {
+                    self.resume = Resume { from: base + from, to: base + stop, line: self.line, anchor: self.anchor, column: self.column };
+                }
+                self.pos = stop;
+                if !self.source.complete() || self.source.limited() {
+                    return Err(self.more(stop));
+                }
+                return Err(self.unterminated(iskey));
+            }
+            match c {
+                Some(b'\n') => {
+                    self.newline(stop);
+                    self.pos = stop + 1;
+                },
+                Some(c) if c < b' ' => self.pos = stop + 1,
+                Some(b'\\') => {
+                    let at = rest.unwrap_or_else(|| {
+                        self.scratch.clear();
+                        from
+                    });
+                    self.copy(at, stop)?;
+                    self.pos = stop;
+                    if let Err(halt) = self.unescape(iskey) {
+                        if let Halt::Error(_) = halt {
+                            self.rewind();
+                        }
+                        return Err(halt);
+                    }
+                    rest = Some(self.pos);
+                    self.check_length(self.scratch.len(), iskey)?;
+                },
+                _ => {
+                    self.pos = stop + 1;
+                    match rest {
+                        Some(at) => {
+                            self.copy(at, stop)?;
+                            return Ok(Held::Scratch);
+                        },
+                        None => return Ok(Held::Window(from, stop))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends the window between `from` and `to` to `scratch`.
+    fn copy(&mut self, from: usize, to: usize) -> Result<(), Halt> {
+        match std::str::from_utf8(&self.source.window()[from..to]) {
+            Ok(text) => self.scratch.push_str(text),
+            Err(e) => return Err(self.invalid_utf8(from + e.valid_up_to()))
+        }
+        return Ok(());
+    }
+
+    /// Decodes the escape sequence at the next byte into `scratch`. Surrogate pairs written as two
+    /// `\uXXXX` escapes are combined, and line continuations contribute nothing.
+    fn unescape(&mut self, iskey: bool) -> Result<(), Halt> {
+        let at = self.pos;
+        let c = match self.byte(at + 1)? {
+            Some(c) => c,
+            None => {
+                self.pos = at + 1;
+                return Err(self.unterminated(iskey));
+            }
+        };
+        let continuations = self.options.allow_line_continuations;
+        let (decoded, len) = match c {
+            b'"' => ('"', 2),
+            b'\\' => ('\\', 2),
+            b'/' => ('/', 2),
+            b'b' => ('\u{8}', 2),
+            b'f' => ('\u{c}', 2),
+            b'n' => ('\n', 2),
+            b'r' => ('\r', 2),
+            b't' => ('\t', 2),
+            b'\'' if self.options.allow_single_quotes => ('\'', 2),
+            b'\n' if continuations => {
+                self.newline(at + 1);
+                self.pos = at + 2;
+                return Ok(());
+            },
+            b'\r' if continuations => {
+                self.pos = match self.byte(at + 2)? {
+                    Some(b'\n') => {
+                        self.newline(at + 2);
+                        at + 3
+                    },
+                    _ => at + 2
+                };
+                return Ok(());
+            },
+            b'u' => {
+                let high = self.read_hex4(at + 2, at)?;
+                if (0xDC00..0xE000).contains(&high) {
+                    return Err(self.escape_error(at, DataErrorKind::LoneSurrogate, "Unpaired low surrogate"));
+                }
+                if (0xD800..0xDC00).contains(&high) {
+                    if self.byte(at + 6)? != Some(b'\\') || self.byte(at + 7)? != Some(b'u') {
+                        return Err(self.escape_error(at, DataErrorKind::LoneSurrogate, "Unpaired high surrogate"));
+                    }
+                    let low = self.read_hex4(at + 8, at)?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.escape_error(at, DataErrorKind::LoneSurrogate, "Unpaired high surrogate"));
+                    }
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                    (char::from_u32(code).unwrap_or_default(), 12)
+                } else {
+                    (char::from_u32(high).unwrap_or_default(), 6)
+                }
+            },
+            _ => {
+                let c = self.char_at(at + 1)?;
+                if continuations && (c == '\u{2028}' || c == '\u{2029}') {
+                    self.pos = at + 1 + c.len_utf8();
+                    return Ok(());
+                }
+                return Err(self.escape_error(at, DataErrorKind::InvalidEscape, &format!("Invalid escape '\\{}'", c)));
+            }
+        };
+        self.scratch.push(decoded);
+        self.pos = at + len;
+        return Ok(());
+    }
+
+    fn read_hex4(&self, from: usize, at: usize) -> Result<u32, Halt> {
+        let mut code: u32 = 0;
+        for i in from..from + 4 {
+            match self.byte(i)?.and_then(|b| (b as char).to_digit(16)) {
+                Some(d) => code = (code << 4) | d,
+                None => return Err(self.escape_error(at, DataErrorKind::InvalidEscape, "Invalid unicode escape, expected 4 hex digits"))
+            }
+        }
+        return Ok(code);
+    }
+
+    /// The character starting at window index `i`, which the window must hold whole.
+    fn char_at(&self, i: usize) -> Result<char, Halt> {
+        let window = self.source.window();
+        let bytes = &window[i..window.len().min(i + 4)];
+        let valid = match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(e) if e.valid_up_to() > 0 => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+            Err(e) if e.error_len().is_none() && (!self.source.complete() || self.source.limited()) => return Err(self.more(window.len())),
+            Err(_) => return Err(self.invalid_utf8(i))
+        };
+        return Ok(valid.chars().next().unwrap_or(' '));
+    }
+
+    /// The byte at window index `i`, or `None` past the end of the input.
+    fn byte(&self, i: usize) -> Result<Option<u8>, Halt> {
+        let window = self.source.window();
+        if i < window.len() {
+            return Ok(Some(window[i]));
+        }
+        if self.source.complete() && !self.source.limited() {
+            return Ok(None);
+        }
+        return Err(self.more(window.len()));
+    }
+
+    /// Skips whitespace and comments, returning whether anything follows them.
+    #[inline(always)]
+    fn skip_space(&mut self) -> Result<bool, Halt> {
+        let comments = self.options.allow_comments;
+        loop {
+            let window = self.source.window();
+            while self.pos < window.len() {
+                match window[self.pos] {
+                    b' ' | b'\t' | b'\r' => self.pos += 1,
+                    b'\n' => {
+                        self.line += 1;
+                        self.anchor = self.source.base() + self.pos + 1;
+                        self.column = 1;
+                        self.pos += 1;
+                    },
+                    b'/' if comments => break,
+                    _ => return Ok(true)
+                }
+            }
+            if self.pos < window.len() {
+                if !self.skip_comment()? {
+                    return Ok(true);
+                }
+            } else if self.source.complete() && !self.source.limited() {
+                return Ok(false);
+            } else {
+                return Err(self.more(self.pos));
+            }
+        }
+    }
+
+    /// Skips a `//` or `/* */` comment when the `/` at the next byte starts one. Returns false,
+    /// consuming nothing, when it does not.
+    fn skip_comment(&mut self) -> Result<bool, Halt> {
+        let at = self.pos;
+        let window = self.source.window();
+        let end = match self.byte(at + 1)? {
+            Some(b'/') => match memchr(b'\n', &window[at + 2..]) {
+                Some(i) => at + 2 + i + 1,
+                None if self.source.complete() && !self.source.limited() => window.len(),
+                None => return Err(self.more(window.len()))
+            },
+            Some(b'*') => match memmem::find(&window[at + 2..], b"*/") {
+                Some(i) => at + 2 + i + 2,
+                None if self.source.complete() && !self.source.limited() => {
+                    return Err(self.error(DataErrorKind::UnexpectedEnd, "Unterminated comment"));
+                },
+                None => return Err(self.more(window.len()))
+            },
+            _ => return Ok(false)
+        };
+        if let Err(e) = std::str::from_utf8(&window[at..end]) {
+            let position = advance(self.position_of(at), &window[at..at + e.valid_up_to()]);
+            return Err(Halt::Error(DataError::new(DataErrorKind::InvalidUtf8, "Invalid UTF-8 sequence", position)));
+        }
+        if let Some(i) = memrchr(b'\n', &window[at..end]) {
+            self.line += memchr_iter(b'\n', &window[at..end]).count();
+            self.anchor = self.source.base() + at + i + 1;
+            self.column = 1;
+        }
+        self.pos = end;
+        return Ok(true);
+    }
+
+    /// Skips to the next `,`, `}` or `]` outside any nested container or quoted string, for `poll_recover`.
+    fn skip_entry(&mut self) -> Result<bool, Halt> {
+        let window = self.source.window();
+        let base = self.source.base();
+        let mut depth = 0;
+        let mut quote: Option<u8> = None;
+        let mut i = self.pos;
+        while i < window.len() {
+            let c = window[i];
+            if c == b'\n' {
+                self.line += 1;
+                self.anchor = base + i + 1;
+                self.column = 1;
+            }
+            if let Some(q) = quote {
+                if c == b'\\' {
+                    if i + 1 == window.len() {
+                        break;
+                    }
+                    if window[i + 1] == b'\n' {
+                        self.line += 1;
+                        self.anchor = base + i + 2;
+                        self.column = 1;
+                    }
+                    i += 1;
+                } else if c == q {
+                    quote = None;
+                }
+                i += 1;
+                continue;
+            }
+            match c {
+                b'"' => quote = Some(c),
+                b'\'' if self.options.allow_single_quotes => quote = Some(c),
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth > 0 => depth -= 1,
+                b',' | b'}' | b']' if depth == 0 => {
+                    self.pos = i;
+                    let frame = &mut self.frames[self.depth - 1];
+                    frame.container = match frame.container {
+                        Container::Map(_) => Container::Map(MapState::AfterValue),
+                        Container::List(_) => Container::List(ListState::AfterValue)
+                    };
+                    return Ok(true);
+                },
+                _ => ()
+            }
+            i += 1;
+        }
+        self.pos = i;
+        if self.source.complete() && !self.source.limited() {
+            return Ok(false);
+        }
+        return Err(self.more(i));
+    }
+
+    #[inline(always)]
+    fn count_entry(&mut self, position: impl FnOnce(&mut Self) -> Position) -> Result<(), Halt> {
+        let max = self.options.limits.max_entries;
+        let frame = &mut self.frames[self.depth - 1];
+        frame.count += 1;
+        match max {
+            Some(max) if frame.count > max => {
+                let position = position(self);
+                return Err(too_many(max, position));
+            },
+            _ => return Ok(())
+        }
+    }
+
+    /// Fails with `StringTooLong` at the start of the token when `len` is over the limit.
+    #[inline(always)]
+    fn check_length(&mut self, len: usize, iskey: bool) -> Result<(), Halt> {
+        match self.options.limits.max_string_length {
+            Some(max) if len > max => {
+                self.rewind();
+                return Err(too_long(max, iskey, self.start));
+            },
+            _ => return Ok(())
+        }
+    }
+
+    /// Goes back to the start of the token being read, so that recovery skips it whole.
+    fn rewind(&mut self) {
+        self.pos = self.start.offset - self.source.base();
+        self.line = self.start.line;
+        self.anchor = self.start.offset;
+        self.column = self.start.column;
+    }
+
+    /// Input ran out at window index `i`: either more is needed, or it has been cut off by the limit.
+    fn more(&self, i: usize) -> Halt {
+        if self.source.limited() {
+            let max = self.options.limits.max_input_size.unwrap_or_default();
+            return Halt::Error(DataError::new(DataErrorKind::InputTooLarge, &format!("Input larger than {} bytes", max), self.position_of(i)));
+        }
+        return Halt::Incomplete;
+    }
+
+    #[cold]
+    fn unterminated(&mut self, iskey: bool) -> Halt {
+        let message = if iskey { "Unexpected end of input" } else { "Unterminated string" };
+        return Halt::Error(DataError::new(DataErrorKind::UnexpectedEnd, message, self.here()));
+    }
+
+    #[cold]
+    fn escape_error(&self, at: usize, kind: DataErrorKind, message: &str) -> Halt {
+        return Halt::Error(DataError::new(kind, message, self.position_of(at)));
+    }
+
+    /// Invalid UTF-8 at window index `i` of the token being read.
+    #[cold]
+    fn invalid_utf8(&self, i: usize) -> Halt {
+        let from = self.start.offset - self.source.base();
+        let position = advance(self.start, &self.source.window()[from..i]);
+        return Halt::Error(DataError::new(DataErrorKind::InvalidUtf8, "Invalid UTF-8 sequence", position));
+    }
+
+    /// An error at the next byte. A byte that is not valid UTF-8 there fails as such instead.
+    #[cold]
+    fn error(&self, kind: DataErrorKind, message: &str) -> Halt {
+        let position = self.position_of(self.pos);
+        let window = self.source.window();
+        if self.pos < window.len() && window[self.pos] >= 0x80 {
+            let bytes = &window[self.pos..window.len().min(self.pos + 4)];
+            if let Err(e) = std::str::from_utf8(bytes) {
+                if e.valid_up_to() == 0 {
+                    if e.error_len().is_none() && (!self.source.complete() || self.source.limited()) {
+                        return self.more(window.len());
+                    }
+                    return Halt::Error(DataError::new(DataErrorKind::InvalidUtf8, "Invalid UTF-8 sequence", position));
+                }
+            }
+        }
+        return Halt::Error(DataError::new(kind, message, position));
+    }
+
+    /// Text of a key or literal just read.
+    #[inline(always)]
+    fn text(&self, held: Held) -> Result<&str, Halt> {
+        match held {
+            Held::Window(from, to) => match std::str::from_utf8(&self.source.window()[from..to]) {
+                Ok(text) => return Ok(text),
+                Err(e) => match self.invalid_utf8(from + e.valid_up_to()) {
+                    Halt::Error(e) => return Err(Halt::Error(e.with_path(self.path()))),
+                    halt => return Err(halt)
+                }
+            },
+            Held::Scratch => return Ok(&self.scratch)
+        }
+    }
+
+    /// Position of window index `i`, which must not be before the last position taken or the last
+    /// line break read.
+    #[inline(always)]
+    fn position_of(&self, i: usize) -> Position {
+        let base = self.source.base();
+        let from = self.anchor - base;
+        let column = self.column + count_chars(&self.source.window()[from..i]);
+        Position { offset: base + i, line: self.line, column }
+    }
+
+    /// Position of the next byte, which becomes the point later positions are counted from.
+    #[inline(always)]
+    fn here(&mut self) -> Position {
+        let position = self.position_of(self.pos);
+        self.anchor = position.offset;
+        self.column = position.column;
+        return position;
+    }
+
+    /// Notes a line break at window index `i`.
+    fn newline(&mut self, i: usize) {
+        self.line += 1;
+        self.anchor = self.source.base() + i + 1;
+        self.column = 1;
+    }
+}
+
+/// Number of characters in `bytes`, counting every byte that does not continue a UTF-8 sequence.
+fn count_chars(bytes: &[u8]) -> usize {
+    return bytes.iter().filter(|&&b| (b as i8) >= -0x40).count();
+}
+
+#[cold]
+fn too_many(max: usize, position: Position) -> Halt {
+    return Halt::Error(DataError::new(DataErrorKind::TooManyEntries, &format!("More than {} entries", max), position));
+}
+
+#[cold]
+fn too_long(max: usize, iskey: bool, position: Position) -> Halt {
+    let message = format!("{} longer than {} bytes", if iskey { "Key" } else { "String" }, max);
+    return Halt::Error(DataError::new(DataErrorKind::StringTooLong, &message, position));
+}
+
+/// Index of the first quote, backslash or control character in `bytes`, or its length. Short
+/// strings, the common case, are quicker to scan a byte at a time than to set `memchr` up for.
+fn special(bytes: &[u8], quote: u8) -> usize {
+    const SHORT: usize = 32;
+    for (i, &b) in bytes.iter().take(SHORT).enumerate() {
+        if b == quote || b == b'\\' || b < b' ' {
+            return i;
+        }
+    }
+    if bytes.len() <= SHORT {
+        return bytes.len();
+    }
+    let rest = &bytes[SHORT..];
+    let stop = memchr2(quote, b'\\', rest).unwrap_or(rest.len());
+    match rest[..stop].iter().position(|&b| b < b' ') {
+        Some(i) => return SHORT + i,
+        None => return SHORT + stop
+    }
+}
+
+/// Position just past the single byte character at `at`.
+fn after(at: Position) -> Position {
+    return Position { offset: at.offset + 1, line: at.line, column: at.column + 1 };
+}
+
+/// Position just past `bytes`, which start at `from`.
+fn advance(from: Position, bytes: &[u8]) -> Position {
+    let offset = from.offset + bytes.len();
+    match memrchr(b'\n', bytes) {
+        Some(i) => {
+            let line = from.line + memchr_iter(b'\n', bytes).count();
+            return Position { offset, line, column: 1 + count_chars(&bytes[i + 1..]) };
+        },
+        None => return Position { offset, line: from.line, column: from.column + count_chars(bytes) }
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+
+/// Which quoted strings are turned into `DataValue::Date`. See `parse_date_with` for the formats.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DateDetection {
+    Off,
+    Iso8601,
+    Extended
+}
+
+/// What to do with a quoted string that has the shape of a date but names no real instant, such
+/// as `"2024-02-30T10:00:00Z"`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InvalidDatePolicy {
+    AsString,
+    Error
+}
+
+/// How a map handles a key that occurs more than once.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// The last value replaces earlier ones.
+    LastWins,
+    /// The first value is kept and later ones are discarded.
+    FirstWins,
+    /// Parsing fails with `DuplicateKey` at the second occurrence.
+    Error,
+    /// All values are gathered, in document order, into a `DataList` under the key.
+    Collect
+}
+
+/// Bounds on the size and shape of the input, for parsing untrusted payloads. `None` means unlimited.
+/// Each limit fails with its own `DataErrorKind`.
+#[derive(Clone)]
+pub struct ParseLimits {
+    /// Maximum nesting of maps and lists, where a top level container is depth 1.
+    pub max_depth: Option<usize>,
+    /// Maximum length in bytes of a string, key or unquoted literal.
+    pub max_string_length: Option<usize>,
+    /// Maximum number of entries in a single map or list.
+    pub max_entries: Option<usize>,
+    /// Maximum number of bytes read from the input.
+    pub max_input_size: Option<usize>
+}
+
+#[allow(dead_code)]
+impl ParseLimits {
+    pub fn unlimited() -> Self {
+        Self { max_depth: None, max_string_length: None, max_entries: None, max_input_size: None }
+    }
+}
+
+impl Default for ParseLimits {
+    /// Only the depth is bounded by default, keeping recursive drops and serialization of the
+    /// resulting tree well within the stack.
+    fn default() -> Self {
+        Self { max_depth: Some(512), ..Self::unlimited() }
+    }
+}
+
+/// Controls which departures from RFC 8259 the parser accepts. The default is the lenient mode the
+/// parser has always had, `strict()` turns every leniency off and `json5()` turns on the JSON5
+/// extensions for hand edited configuration files.
+#[derive(Clone)]
+pub struct ParseOptions {
+    /// Map keys without quotes, e.g. `{ num: 1 }`.
+    pub allow_unquoted_keys: bool,
+    /// Bare words read as strings, e.g. `[ abc ]`. Also covers text that starts like a number but
+    /// goes on with other characters, e.g. `1.2.3`. A number that is cut short, e.g. `1e`, or
+    /// beyond the `f64` range is still an error.
+    pub allow_unquoted_strings: bool,
+    /// `Infinity`, `-Infinity` and `NaN` as numbers.
+    pub allow_non_finite_numbers: bool,
+    /// A `+` sign in front of a number.
+    pub allow_leading_plus: bool,
+    /// Integer parts with leading zeros, e.g. `007`.
+    pub allow_leading_zeros: bool,
+    /// Unescaped control characters (below U+0020) inside quoted strings and keys.
+    pub allow_control_characters: bool,
+    /// `// line` and `/* block */` comments wherever whitespace is allowed.
+    pub allow_comments: bool,
+    /// Strings and keys delimited by `'` as well as `"`.
+    pub allow_single_quotes: bool,
+    /// A `,` after the last entry of a map or list.
+    pub allow_trailing_commas: bool,
+    /// Hexadecimal integers, e.g. `0x1F`.
+    pub allow_hex_numbers: bool,
+    /// Numbers with a leading or trailing decimal point, e.g. `.5` or `5.`.
+    pub allow_bare_decimal_point: bool,
+    /// A backslash at the end of a line inside a string, continuing the string on the next line.
+    pub allow_line_continuations: bool,
+    /// Anything but whitespace and comments after the top level value, which is then ignored.
+    /// Has no effect on `documents`, which reads such content as further documents.
+    pub allow_trailing_content: bool,
+    /// Which quoted strings are read as dates.
+    pub date_detection: DateDetection,
+    /// Keys whose values are never read as dates, including the items of lists held under the key.
+    pub date_exempt_keys: HashSet<String>,
+    /// Whether out of range dates are kept as strings or reported as `DateOutOfRange` errors.
+    pub invalid_dates: InvalidDatePolicy,
+    /// What happens when a key occurs more than once in the same map.
+    pub duplicate_keys: DuplicateKeyPolicy,
+    pub limits: ParseLimits
+}
+
+#[allow(dead_code)]
+impl ParseOptions {
+    pub fn lenient() -> Self {
+        Self {
+            allow_unquoted_keys: true,
+            allow_unquoted_strings: true,
+            allow_non_finite_numbers: true,
+            allow_leading_plus: true,
+            allow_leading_zeros: true,
+            allow_control_characters: true,
+            allow_comments: false,
+            allow_single_quotes: false,
+            allow_trailing_commas: false,
+            allow_hex_numbers: false,
+            allow_bare_decimal_point: false,
+            allow_line_continuations: false,
+            allow_trailing_content: true,
+            date_detection: DateDetection::Iso8601,
+            date_exempt_keys: HashSet::new(),
+            invalid_dates: InvalidDatePolicy::AsString,
+            duplicate_keys: DuplicateKeyPolicy::LastWins,
+            limits: ParseLimits::default()
+        }
+    }
+
+    pub fn strict() -> Self {
+        Self {
+            allow_unquoted_keys: false,
+            allow_unquoted_strings: false,
+            allow_non_finite_numbers: false,
+            allow_leading_plus: false,
+            allow_leading_zeros: false,
+            allow_control_characters: false,
+            allow_comments: false,
+            allow_single_quotes: false,
+            allow_trailing_commas: false,
+            allow_hex_numbers: false,
+            allow_bare_decimal_point: false,
+            allow_line_continuations: false,
+            allow_trailing_content: false,
+            date_detection: DateDetection::Iso8601,
+            date_exempt_keys: HashSet::new(),
+            invalid_dates: InvalidDatePolicy::AsString,
+            duplicate_keys: DuplicateKeyPolicy::LastWins,
+            limits: ParseLimits::default()
+        }
+    }
+
+    pub fn json5() -> Self {
+        Self {
+            allow_comments: true,
+            allow_single_quotes: true,
+            allow_trailing_commas: true,
+            allow_hex_numbers: true,
+            allow_bare_decimal_point: true,
+            allow_line_continuations: true,
+            ..Self::lenient()
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::lenient()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::DataErrorKind;
+    use super::super::{parse_borrowed_with, parse_with};
+    use super::ParseOptions;
+
+    /// Turns on the leniency a case needs.
+    type Allow = fn(&mut ParseOptions);
+
+    #[test]
+    fn strict_rejects_each_leniency() {
+        let cases: [(&str, Allow, DataErrorKind); 14] = [
+            ("{num: 1}", |o| o.allow_unquoted_keys = true, DataErrorKind::UnquotedKey),
+            ("[abc]", |o| o.allow_unquoted_strings = true, DataErrorKind::UnquotedString),
+            ("[Infinity]", |o| o.allow_non_finite_numbers = true, DataErrorKind::NonFiniteNumber),
+            ("[NaN]", |o| o.allow_non_finite_numbers = true, DataErrorKind::NonFiniteNumber),
+            ("[+1]", |o| o.allow_leading_plus = true, DataErrorKind::InvalidNumber),
+            ("[007]", |o| o.allow_leading_zeros = true, DataErrorKind::InvalidNumber),
+            ("[\"a\tb\"]", |o| o.allow_control_characters = true, DataErrorKind::ControlCharacter),
+            ("[1 /* c */]", |o| o.allow_comments = true, DataErrorKind::ExpectedListSeparator),
+            ("['a']", |o| o.allow_single_quotes = true, DataErrorKind::UnquotedString),
+            ("[1, 2,]", |o| o.allow_trailing_commas = true, DataErrorKind::UnexpectedCharacter),
+            ("[0x1F]", |o| o.allow_hex_numbers = true, DataErrorKind::InvalidNumber),
+            ("[.5]", |o| o.allow_bare_decimal_point = true, DataErrorKind::InvalidNumber),
+            ("[\"a\\\nb\"]", |o| o.allow_line_continuations = true, DataErrorKind::InvalidEscape),
+            ("[1] [2]", |o| o.allow_trailing_content = true, DataErrorKind::TrailingContent)
+        ];
+        for (input, allow, kind) in cases {
+            let strict = ParseOptions::strict();
+            assert_eq!(parse_with(input, &strict).map_err(|e| e.kind).err(), Some(kind), "{}", input);
+            assert_eq!(parse_borrowed_with(input, &strict).map_err(|e| e.kind).err(), Some(kind), "{}", input);
+            let mut lenient = ParseOptions::strict();
+            allow(&mut lenient);
+            assert!(parse_with(input, &lenient).is_ok(), "{}", input);
+        }
+    }
+}
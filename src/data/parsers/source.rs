@@ -0,0 +1,135 @@
+use std::io::{self, ErrorKind, Read};
+
+const BUFFER_SIZE: usize = 8192;
+
+/// Bytes of input for `Lexer`, made available a window at a time. The lexer reads the window in
+/// place and asks for more only when a token runs off its end.
+pub trait Source {
+    /// Input read so far and not yet released. Starts at byte `base()` of the input.
+    fn window(&self) -> &[u8];
+
+    fn base(&self) -> usize;
+
+    /// Whether the window ends where the input does.
+    fn complete(&self) -> bool;
+
+    /// Whether the window ends at the input size limit, with more input beyond it.
+    fn limited(&self) -> bool;
+
+    /// Releases the window before index `keep` and waits for more input to follow the rest, unless
+    /// the window is already `complete` or `limited`.
+    fn fill(&mut self, keep: usize) -> io::Result<()>;
+}
+
+/// Input held in memory as a whole.
+pub struct SliceSource<'a> {
+    bytes: &'a [u8],
+    limited: bool
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(bytes: &'a [u8], max_input: Option<usize>) -> Self {
+        match max_input {
+            Some(max) if bytes.len() > max => return Self { bytes: &bytes[..max], limited: true },
+            _ => return Self { bytes, limited: false }
+        }
+    }
+}
+
+impl Source for SliceSource<'_> {
+    fn window(&self) -> &[u8] {
+        self.bytes
+    }
+
+    fn base(&self) -> usize {
+        0
+    }
+
+    fn complete(&self) -> bool {
+        !self.limited
+    }
+
+    fn limited(&self) -> bool {
+        self.limited
+    }
+
+    fn fill(&mut self, _keep: usize) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Input from a `Read`, through a buffer that starts at 8 KiB and doubles whenever a single token
+/// fills it, so that no more than the token being read and the rest of the last read are held.
+pub struct ReadSource<R: Read> {
+    inner: R,
+    buf: Vec<u8>,
+    len: usize,
+    base: usize,
+    eof: bool,
+    limited: bool,
+    max_input: Option<usize>
+}
+
+impl<R: Read> ReadSource<R> {
+    pub fn new(inner: R, max_input: Option<usize>) -> Self {
+        Self { inner, buf: vec![0u8; BUFFER_SIZE], len: 0, base: 0, eof: false, limited: false, max_input }
+    }
+
+    fn read(&mut self) -> io::Result<()> {
+        loop {
+            let room = match self.max_input {
+                Some(max) => (max - self.base - self.len).min(self.buf.len() - self.len),
+                None => self.buf.len() - self.len
+            };
+            // At the limit, a single byte more tells a larger input from one that ends right there.
+            let res = match room {
+                0 => self.inner.read(&mut [0u8]),
+                _ => self.inner.read(&mut self.buf[self.len..self.len + room])
+            };
+            match res {
+                Ok(0) => self.eof = true,
+                Ok(_) if room == 0 => self.limited = true,
+                Ok(n) => self.len += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.eof = true;
+                    return Err(e);
+                }
+            }
+            return Ok(());
+        }
+    }
+}
+
+impl<R: Read> Source for ReadSource<R> {
+    fn window(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn base(&self) -> usize {
+        self.base
+    }
+
+    fn complete(&self) -> bool {
+        self.eof
+    }
+
+    fn limited(&self) -> bool {
+        self.limited
+    }
+
+    fn fill(&mut self, keep: usize) -> io::Result<()> {
+        if keep > 0 {
+            self.buf.copy_within(keep..self.len, 0);
+            self.len -= keep;
+            self.base += keep;
+        }
+        if self.eof || self.limited {
+            return Ok(());
+        }
+        if self.len == self.buf.len() {
+            self.buf.resize(2 * self.buf.len(), 0);
+        }
+        return self.read();
+    }
+}
@@ -1,15 +1,43 @@
-use std::str::Chars;
+use super::super::{DataError, DataErrorKind, Position};
 
+/// Reads the text of a number or date byte by byte. Both grammars are ASCII, so a byte outside
+/// ASCII comes out as a character that matches nothing in them. `next` returns NUL at the end of
+/// the text; `at_end` tells that apart from a NUL in the text itself.
 pub struct StringReader<'a> {
-    pub chars: Chars<'a>
+    bytes: &'a [u8],
+    end: bool,
+    kind: DataErrorKind,
+    last_position: Position,
+    position: Position
 }
 
 impl<'a> StringReader<'a> {
+    pub fn new(s: &'a str, kind: DataErrorKind) -> Self {
+        Self { bytes: s.as_bytes(), end: false, kind, last_position: Position::start(), position: Position::start() }
+    }
+
     pub fn next(&mut self) -> char {
-        let res = self.chars.next();
-        match res {
-            Option::Some(c) => return c,
-            Option::None => return 0u8 as char
+        self.last_position = self.position;
+        match self.bytes.get(self.position.offset) {
+            Some(&b) => {
+                self.position.offset += 1;
+                self.position.column += 1;
+                return b as char;
+            },
+            None => {
+                self.end = true;
+                return 0u8 as char;
+            }
         }
     }
-}
\ No newline at end of file
+
+    /// Whether the most recent `next` ran past the end of the text.
+    pub fn at_end(&self) -> bool {
+        self.end
+    }
+
+    /// Error of this reader's kind located at the character most recently returned by `next`.
+    pub fn error(&self, message: &str) -> DataError {
+        DataError::new(self.kind, message, self.last_position)
+    }
+}
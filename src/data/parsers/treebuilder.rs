@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+use super::super::datalist::DataList;
+use super::super::datamap::DataMap;
+use super::super::{DataError, DataErrorKind, DataValue, Position};
+use super::{DuplicateKeyPolicy, Event};
+
+struct PartialMap {
+    map: DataMap,
+    key: String,
+    /// Keys whose values have been gathered into a list, under `DuplicateKeyPolicy::Collect`.
+    collected: Option<HashSet<String>>
+}
+
+enum Partial {
+    Map(PartialMap),
+    List(DataList)
+}
+
+/// Assembles a `DataValue` tree from a stream of `Event`s.
+pub struct TreeBuilder {
+    stack: Vec<Partial>,
+    duplicate_keys: DuplicateKeyPolicy
+}
+
+impl TreeBuilder {
+    pub fn new(duplicate_keys: DuplicateKeyPolicy) -> Self {
+        Self { stack: Vec::new(), duplicate_keys }
+    }
+
+    /// Consumes one event, returning the finished value once the outermost value is complete.
+    /// `position` is where the event starts in the input.
+    pub fn accept(&mut self, event: Event, position: Position) -> Result<Option<DataValue>, DataError> {
+        let value = match event {
+            Event::StartMap => {
+                self.stack.push(Partial::Map(PartialMap { map: DataMap::new(), key: String::new(), collected: None }));
+                return Ok(None);
+            },
+            Event::StartList => {
+                self.stack.push(Partial::List(DataList::new()));
+                return Ok(None);
+            },
+            Event::Key(k) => {
+                if let Some(Partial::Map(partial)) = self.stack.last_mut() {
+                    let duplicate = self.duplicate_keys == DuplicateKeyPolicy::Error && partial.map.has(&k);
+                    partial.key = k;
+                    if duplicate {
+                        return Err(DataError::new(DataErrorKind::DuplicateKey, &format!("Duplicate key '{}'", partial.key), position));
+                    }
+                }
+                return Ok(None);
+            },
+            Event::EndMap => match self.stack.pop() {
+                Some(Partial::Map(partial)) => DataValue::DataMap(partial.map),
+                _ => return Ok(None)
+            },
+            Event::EndList => match self.stack.pop() {
+                Some(Partial::List(list)) => DataValue::DataList(list),
+                _ => return Ok(None)
+            },
+            Event::Value(v) => v
+        };
+        match self.stack.last_mut() {
+            Some(Partial::Map(partial)) => {
+                insert(partial, value, self.duplicate_keys);
+                return Ok(None);
+            },
+            Some(Partial::List(list)) => {
+                list.push(value);
+                return Ok(None);
+            },
+            None => return Ok(Some(value))
+        }
+    }
+
+    /// Whether no container has been opened, or every one opened has been closed.
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Closes the containers left open by input that ended early, returning the partial value, or
+    /// `DataValue::None` when nothing was read.
+    pub fn close(&mut self) -> DataValue {
+        loop {
+            let event = match self.stack.last() {
+                Some(Partial::Map(_)) => Event::EndMap,
+                Some(Partial::List(_)) => Event::EndList,
+                None => return DataValue::None
+            };
+            if let Ok(Some(value)) = self.accept(event, Position::start()) {
+                return value;
+            }
+        }
+    }
+}
+
+fn insert(partial: &mut PartialMap, value: DataValue, duplicate_keys: DuplicateKeyPolicy) {
+    if let DuplicateKeyPolicy::LastWins | DuplicateKeyPolicy::Error = duplicate_keys {
+        partial.map.put_owned(std::mem::take(&mut partial.key), value);
+        return;
+    }
+    let key = &partial.key;
+    match partial.map.get_mut(key) {
+        Some(existing) => match duplicate_keys {
+            DuplicateKeyPolicy::FirstWins => (),
+            DuplicateKeyPolicy::Collect => {
+                let collected = partial.collected.get_or_insert_with(HashSet::new);
+                if collected.contains(key) {
+                    if let DataValue::DataList(list) = existing {
+                        list.push(value);
+                    }
+                } else {
+                    let mut list = DataList::new();
+                    list.push(std::mem::replace(existing, DataValue::None));
+                    list.push(value);
+                    *existing = DataValue::DataList(list);
+                    collected.insert(key.clone());
+                }
+            },
+            _ => *existing = value
+        },
+        None => partial.map.put_owned(std::mem::take(&mut partial.key), value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::serializers::{serialize_with, SerializeOptions};
+    use super::super::super::DataErrorKind;
+    use super::super::{parse_borrowed_with, parse_with, BorrowedValue, DuplicateKeyPolicy, ParseOptions};
+
+    const INPUT: &str = "{\"a\": 1, \"b\": [2], \"a\": [3], \"c\": {\"b\": 4, \"b\": 5}, \"a\": 6}";
+
+    fn parsed(policy: DuplicateKeyPolicy) -> Result<String, (DataErrorKind, usize)> {
+        let options = ParseOptions { duplicate_keys: policy, ..ParseOptions::default() };
+        let text = |v| serialize_with(&v, &SerializeOptions::compact());
+        let borrowed = parse_borrowed_with(INPUT, &options).map(BorrowedValue::into_owned);
+        let res = parse_with(INPUT, &options).map(text).map_err(|e| (e.kind, e.position.offset));
+        assert_eq!(borrowed.map(text).map_err(|e| (e.kind, e.position.offset)), res);
+        return res;
+    }
+
+    #[test]
+    fn each_duplicate_key_policy() {
+        assert_eq!(parsed(DuplicateKeyPolicy::LastWins), Ok("{\"a\":6,\"b\":[2],\"c\":{\"b\":5}}".to_string()));
+        assert_eq!(parsed(DuplicateKeyPolicy::FirstWins), Ok("{\"a\":1,\"b\":[2],\"c\":{\"b\":4}}".to_string()));
+        assert_eq!(parsed(DuplicateKeyPolicy::Collect), Ok("{\"a\":[1,[3],6],\"b\":[2],\"c\":{\"b\":[4,5]}}".to_string()));
+        assert_eq!(parsed(DuplicateKeyPolicy::Error), Err((DataErrorKind::DuplicateKey, 19)));
+    }
+}
@@ -0,0 +1,457 @@
+pub(super) mod canonical;
+mod serializeoptions;
+
+use std::fmt::{self, Write};
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use super::{datalist::DataList, datamap::DataMap, DataError, DataPath, DataValue, SerializableData};
+
+pub use serializeoptions::{LineEnding, SerializeOptions};
+
+/// Serializes a value, map or list so that `parse` reads back the same value, with one exception:
+/// a `DataValue::String` that looks like a date, such as `"2024-08-25T12:15:28Z"`, is written as
+/// that string and so reads back as a `DataValue::Date`, unless parsed with `DateDetection::Off`.
+#[allow(dead_code)]
+pub fn serialize<T: SerializableData>(value: &T) -> String {
+    return serialize_with(value, &SerializeOptions::default());
+}
+
+#[allow(dead_code)]
+pub fn serialize_with<T: SerializableData>(value: &T, options: &SerializeOptions) -> String {
+    let mut s = String::new();
+    value.serialize_to_string(&mut s, 0, options);
+    return s;
+}
+
+/// Writes a value, map or list to `w` as it is serialized, without building the text in memory.
+/// The writes are small, so a file or socket is best wrapped in a `BufWriter`.
+#[allow(dead_code)]
+pub fn serialize_to_writer<T: SerializableData, W: io::Write>(value: &T, w: &mut W, options: &SerializeOptions) -> io::Result<()> {
+    let mut sink = IoSink { inner: w, error: None };
+    let res = value.serialize_to(&mut Output::new(&mut sink), 0, options);
+    return sink.result(res);
+}
+
+/// Writes the items of `items` to `w` as a list, one at a time. See `ListWriter`.
+#[allow(dead_code)]
+pub fn serialize_iter_to_writer<I, W>(items: I, w: &mut W, options: &SerializeOptions) -> io::Result<()>
+where
+    I: IntoIterator<Item = DataValue>,
+    W: io::Write
+{
+    let mut list = ListWriter::new(w, options);
+    for item in items {
+        list.push(&item)?;
+    }
+    return list.finish();
+}
+
+/// Serializes a value, map or list in the canonical form of RFC 8785 (JSON Canonicalization
+/// Scheme): keys sorted, no whitespace, numbers as ECMAScript writes them and minimal escaping, so
+/// that equal values always give the same bytes. Fails with `NonFiniteNumber` on NaN and infinities.
+///
+/// JSON has no dates, only strings, so to canonicalize a document parse it with
+/// `DateDetection::Off`. A `DataValue::Date` is written as its `to_string()`, which need not be the
+/// text it was read from: `"2024-08-25T12:15:28.000Z"` would become `"2024-08-25T12:15:28Z"`.
+#[allow(dead_code)]
+pub fn serialize_canonical<T: SerializableData>(value: &T) -> Result<String, DataError> {
+    let mut s = String::new();
+    value.serialize_canonical_to(&mut s, &mut DataPath::new())?;
+    return Ok(s);
+}
+
+/// SHA-256 of the canonical form, the same for maps that differ only in the order of their keys.
+/// As for `serialize_canonical`, documents should be parsed with `DateDetection::Off`.
+#[allow(dead_code)]
+pub fn fingerprint<T: SerializableData>(value: &T) -> Result<[u8; 32], DataError> {
+    let canonical = serialize_canonical(value)?;
+    return Ok(Sha256::digest(canonical.as_bytes()).into());
+}
+
+/// Destination of the serializers. Counts the characters on the current line, so that pretty
+/// output can tell whether a container still fits within `max_line_width`.
+pub struct Output<'a> {
+    sink: &'a mut dyn fmt::Write,
+    column: usize
+}
+
+impl<'a> Output<'a> {
+    pub fn new(sink: &'a mut dyn fmt::Write) -> Self {
+        Self { sink, column: 0 }
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Write for Output<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match s.rfind('\n') {
+            Some(i) => self.column = s[i + 1..].chars().count(),
+            None => self.column += s.chars().count()
+        }
+        return self.sink.write_str(s);
+    }
+}
+
+/// Lets the serializers write to an `io::Write`, holding on to the error that `fmt::Write` cannot
+/// carry.
+struct IoSink<'w, W: io::Write> {
+    inner: &'w mut W,
+    error: Option<io::Error>
+}
+
+impl<W: io::Write> IoSink<'_, W> {
+    fn result(&mut self, res: fmt::Result) -> io::Result<()> {
+        match res {
+            Ok(()) => return Ok(()),
+            Err(_) => return Err(self.error.take().unwrap_or_else(|| io::Error::other("Serialization failed")))
+        }
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoSink<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        return self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        });
+    }
+}
+
+/// Writes a list to an `io::Write` item by item, so that an export never holds all of its items in
+/// memory. Pretty output puts each item on a line of its own, as the list cannot be measured up
+/// front. Nothing is written until the first `push`, and the list is only complete after `finish`.
+pub struct ListWriter<'w, W: io::Write> {
+    sink: IoSink<'w, W>,
+    options: SerializeOptions,
+    column: usize,
+    count: usize
+}
+
+#[allow(dead_code)]
+impl<'w, W: io::Write> ListWriter<'w, W> {
+    pub fn new(w: &'w mut W, options: &SerializeOptions) -> Self {
+        Self { sink: IoSink { inner: w, error: None }, options: options.clone(), column: 0, count: 0 }
+    }
+
+    pub fn push(&mut self, val: &DataValue) -> io::Result<()> {
+        let mut out = Output { sink: &mut self.sink, column: self.column };
+        let res = write_item(&mut out, self.count == 0, val, &self.options);
+        self.column = out.column;
+        self.count += 1;
+        return self.sink.result(res);
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        let res = if self.count == 0 {
+            self.sink.write_str("[]")
+        } else if self.options.pretty {
+            self.sink.write_str(self.options.line_ending.as_str()).and_then(|_| self.sink.write_char(']'))
+        } else {
+            self.sink.write_char(']')
+        };
+        return self.sink.result(res);
+    }
+}
+
+fn write_item(out: &mut Output, first: bool, val: &DataValue, options: &SerializeOptions) -> fmt::Result {
+    out.write_char(if first { '[' } else { ',' })?;
+    if options.pretty {
+        out.write_str(options.line_ending.as_str())?;
+        out.write_str(&options.indent)?;
+    }
+    // Whether another item follows is not known yet, so room is kept for a separator.
+    return write_entry(out, None, val, 0, 1, options);
+}
+
+/// Writes a map, in key order with `sort_keys`. `trailing` is the number of characters that will
+/// follow on the same line, taken into account when deciding whether it fits on one line, with
+/// `usize::MAX` for a map inside a container that is already being written on one line.
+pub(super) fn write_map(out: &mut Output, map: &DataMap, indent: usize, trailing: usize, options: &SerializeOptions) -> fmt::Result {
+    if options.sort_keys && !map.is_sorted() {
+        let mut entries: Vec<(Option<&str>, &DataValue)> = map.iter().map(|(key, val)| (Some(key.as_str()), val)).collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        return write_container(out, '{', '}', entries.into_iter(), indent, trailing, options);
+    } else {
+        let entries = map.iter().map(|(key, val)| (Some(key.as_str()), val));
+        return write_container(out, '{', '}', entries, indent, trailing, options);
+    }
+}
+
+pub(super) fn write_list(out: &mut Output, list: &DataList, indent: usize, trailing: usize, options: &SerializeOptions) -> fmt::Result {
+    return write_container(out, '[', ']', list.iter().map(|val| (None, val)), indent, trailing, options);
+}
+
+/// Writes the entries of a map, each with its key, or of a list between `open` and `close`. Pretty
+/// output keeps the whole container on one line when it fits within `max_line_width`, and
+/// otherwise gives each entry a line of its own, each entry in turn getting the same choice.
+fn write_container<'a, I>(out: &mut Output, open: char, close: char, entries: I, indent: usize, trailing: usize, options: &SerializeOptions) -> fmt::Result
+where
+    I: ExactSizeIterator<Item = (Option<&'a str>, &'a DataValue)> + Clone
+{
+    if entries.len() == 0 {
+        out.write_char(open)?;
+        return out.write_char(close);
+    }
+    let flat = !options.pretty || trailing == usize::MAX || match options.max_line_width {
+        Some(width) => {
+            let used = out.column() + trailing;
+            width >= used && entries_width(&mut entries.clone(), width - used + 1, options).is_some()
+        },
+        None => false
+    };
+    out.write_char(open)?;
+    if flat {
+        for (i, (key, val)) in entries.enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+                if options.pretty {
+                    out.write_char(' ')?;
+                }
+            }
+            write_entry(out, key, val, indent, usize::MAX, options)?;
+        }
+        return out.write_char(close);
+    }
+    let newline = options.line_ending.as_str();
+    let count = entries.len();
+    out.write_str(newline)?;
+    for (i, (key, val)) in entries.enumerate() {
+        for _ in 0..=indent {
+            out.write_str(&options.indent)?;
+        }
+        let last = i + 1 == count;
+        write_entry(out, key, val, indent, if last { 0 } else { 1 }, options)?;
+        if !last {
+            out.write_char(',')?;
+        }
+        out.write_str(newline)?;
+    }
+    for _ in 0..indent {
+        out.write_str(&options.indent)?;
+    }
+    return out.write_char(close);
+}
+
+/// Writes a key, if any, and a value.
+fn write_entry(out: &mut Output, key: Option<&str>, val: &DataValue, indent: usize, trailing: usize, options: &SerializeOptions) -> fmt::Result {
+    if let Some(key) = key {
+        write_string(out, key, options)?;
+        out.write_char(':')?;
+        if options.space_after_colon {
+            out.write_char(' ')?;
+        }
+    }
+    match val {
+        DataValue::DataMap(map) => return write_map(out, map, indent + 1, trailing, options),
+        DataValue::DataList(list) => return write_list(out, list, indent + 1, trailing, options),
+        _ => return val.serialize_to(out, indent + 1, options)
+    }
+}
+
+/// Width of the entries written on one line, brackets included, or `None` once it reaches `limit`,
+/// so that measuring a large container stops early.
+fn entries_width<'a>(entries: &mut dyn Iterator<Item = (Option<&'a str>, &'a DataValue)>, limit: usize, options: &SerializeOptions) -> Option<usize> {
+    let mut width = 2;
+    for (i, (key, val)) in entries.enumerate() {
+        if i > 0 {
+            width += 2;
+        }
+        if let Some(key) = key {
+            let mut s = String::new();
+            let _ = write_string(&mut Output::new(&mut s), key, options);
+            width += s.chars().count() + 1 + usize::from(options.space_after_colon);
+        }
+        if width >= limit {
+            return None;
+        }
+        width += match val {
+            DataValue::DataMap(map) => entries_width(&mut map.iter().map(|(key, val)| (Some(key.as_str()), val)), limit - width, options)?,
+            DataValue::DataList(list) => entries_width(&mut list.iter().map(|val| (None, val)), limit - width, options)?,
+            _ => scalar_width(val, options)
+        };
+        if width >= limit {
+            return None;
+        }
+    }
+    return Some(width);
+}
+
+fn scalar_width(val: &DataValue, options: &SerializeOptions) -> usize {
+    let mut s = String::new();
+    val.serialize_to_string(&mut s, 0, options);
+    return s.chars().count();
+}
+
+/// Writes `s` as a quoted string, escaping quotes, backslashes and control characters, and with
+/// `ascii_only` everything beyond ASCII.
+pub(super) fn write_string(out: &mut Output, s: &str, options: &SerializeOptions) -> fmt::Result {
+    out.write_char('"')?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{08}' => "\\b",
+            '\u{0C}' => "\\f",
+            c if c < ' ' || (options.ascii_only && !c.is_ascii()) => "",
+            _ => continue
+        };
+        out.write_str(&s[start..i])?;
+        start = i + c.len_utf8();
+        if !escaped.is_empty() {
+            out.write_str(escaped)?;
+        } else {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                write!(out, "\\u{:04x}", unit)?;
+            }
+        }
+    }
+    out.write_str(&s[start..])?;
+    return out.write_char('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parsers::{parse, parse_with, DateDetection, ParseOptions};
+    use super::*;
+
+    #[test]
+    fn deepest_default_nesting_serializes() {
+        let depth = 512;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let value = parse(&input).unwrap();
+        assert_eq!(serialize_with(&value, &SerializeOptions::compact()), input);
+        assert_eq!(parse(&serialize_with(&value, &SerializeOptions::pretty())).map(|v| serialize_with(&v, &SerializeOptions::compact())).unwrap(), input);
+        assert_eq!(serialize_canonical(&value).unwrap(), input);
+
+        let input = format!("{}1{}", "{\"a\":".repeat(depth), "}".repeat(depth));
+        let value = parse(&input).unwrap();
+        assert_eq!(serialize_with(&value, &SerializeOptions::compact()), input);
+        assert_eq!(serialize_canonical(&value).unwrap(), input);
+    }
+
+    #[test]
+    fn serialized_values_parse_back_equal() {
+        let doc = concat!(
+            "{\"s\": \"q\\\" b\\\\ \\u0001 \\t\\n é 😀 \\u2028\", \"i\": -42, \"u\": 18446744073709551615, ",
+            "\"f\": [0.1, -2.5e-300, 1.7976931348623157e308, 1e21, 3.0], ",
+            "\"d\": [\"2024-08-25T12:15:28.123456Z\", \"2024-08-25T12:15:28.123456789+10:00\", \"2024-08-25T12:15:28-05:30\", ",
+            "\"2016-12-31T23:59:60.5Z\"], \"n\": {\"z\": null, \"a\": [true, false, {}, []]}, \"\": \"\"}"
+        );
+        let value = parse(doc).unwrap();
+        let options = [
+            SerializeOptions::pretty(),
+            SerializeOptions::compact(),
+            SerializeOptions::readable(),
+            SerializeOptions::ascii(),
+            SerializeOptions { sort_keys: true, line_ending: LineEnding::CrLf, max_line_width: Some(20), ..SerializeOptions::pretty() }
+        ];
+        for options in options {
+            let text = serialize_with(&value, &options);
+            assert_eq!(parse(&text).unwrap(), value, "{}", text);
+        }
+        assert!(serialize(&value).contains("\"2024-08-25T12:15:28.123456Z\""));
+        assert!(serialize(&value).contains("\"2024-08-25T12:15:28.123456789+10:00\""));
+    }
+
+    fn readable(input: &str, width: usize) -> String {
+        let options = SerializeOptions { max_line_width: Some(width), ..SerializeOptions::readable() };
+        return serialize_with(&parse(input).unwrap(), &options);
+    }
+
+    #[test]
+    fn containers_break_only_when_their_line_exceeds_the_width() {
+        let input = "{\"b\": [1, 2, 3], \"a\": [1, 2, 3]}";
+        let golden = [
+            (32, "{\"a\": [1, 2, 3], \"b\": [1, 2, 3]}"),
+            (31, "{\n  \"a\": [1, 2, 3],\n  \"b\": [1, 2, 3]\n}"),
+            (17, "{\n  \"a\": [1, 2, 3],\n  \"b\": [1, 2, 3]\n}"),
+            // The comma after the first entry counts towards its line, the closing bracket does not.
+            (16, "{\n  \"a\": [\n    1,\n    2,\n    3\n  ],\n  \"b\": [1, 2, 3]\n}"),
+            (15, "{\n  \"a\": [\n    1,\n    2,\n    3\n  ],\n  \"b\": [\n    1,\n    2,\n    3\n  ]\n}")
+        ];
+        for (width, expected) in golden {
+            assert_eq!(readable(input, width), expected, "width {}", width);
+        }
+        // Widths count characters, not bytes.
+        assert_eq!(readable("[\"\u{e9}\u{e9}\"]", 6), "[\"\u{e9}\u{e9}\"]");
+        assert_eq!(readable("[\"\u{e9}\u{e9}\"]", 5), "[\n  \"\u{e9}\u{e9}\"\n]");
+        assert_eq!(readable("[[], {}]", 1), "[\n  [],\n  {}\n]");
+    }
+
+    fn list_text(items: &[&str], options: &SerializeOptions) -> String {
+        let mut bytes = Vec::new();
+        let mut list = ListWriter::new(&mut bytes, options);
+        for item in items {
+            list.push(&parse(item).unwrap()).unwrap();
+        }
+        list.finish().unwrap();
+        return String::from_utf8(bytes).unwrap();
+    }
+
+    #[test]
+    fn list_writer_writes_empty_lists() {
+        for options in [SerializeOptions::pretty(), SerializeOptions::compact(), SerializeOptions::readable()] {
+            assert_eq!(list_text(&[], &options), "[]");
+            let mut bytes = Vec::new();
+            serialize_iter_to_writer(Vec::new(), &mut bytes, &options).unwrap();
+            assert_eq!(bytes, b"[]");
+        }
+    }
+
+    #[test]
+    fn list_writer_output_matches_the_serializers() {
+        let items = ["{\"b\": [1, 2], \"a\": null}", "\"x\"", "[]", "[{\"c\": true}]"];
+        let list = parse(&format!("[{}]", items.join(", "))).unwrap();
+        for options in [SerializeOptions::compact(), SerializeOptions::pretty(), SerializeOptions::ascii()] {
+            assert_eq!(list_text(&items, &options), serialize_with(&list, &options));
+        }
+        // Readable output cannot keep the list on one line, but its items still fit the width.
+        let expected = "[\n  {\"a\": null, \"b\": [1, 2]},\n  \"x\",\n  [],\n  [{\"c\": true}]\n]";
+        assert_eq!(list_text(&items, &SerializeOptions::readable()), expected);
+        let narrow = SerializeOptions { max_line_width: Some(15), ..SerializeOptions::readable() };
+        let expected = "[\n  {\n    \"a\": null,\n    \"b\": [1, 2]\n  },\n  \"x\",\n  [],\n  [\n    {\"c\": true}\n  ]\n]";
+        assert_eq!(list_text(&items, &narrow), expected);
+        assert_eq!(list_text(&["1"], &SerializeOptions { line_ending: LineEnding::CrLf, ..SerializeOptions::readable() }), "[\r\n  1\r\n]");
+    }
+
+    #[test]
+    fn list_writer_reports_write_errors() {
+        struct Full;
+        impl io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                return Err(io::Error::new(io::ErrorKind::StorageFull, "full"));
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                return Ok(());
+            }
+        }
+        let mut full = Full;
+        let mut list = ListWriter::new(&mut full, &SerializeOptions::compact());
+        assert_eq!(list.push(&DataValue::Null).unwrap_err().kind(), io::ErrorKind::StorageFull);
+        assert_eq!(serialize_to_writer(&DataValue::Null, &mut Full, &SerializeOptions::compact()).unwrap_err().kind(), io::ErrorKind::StorageFull);
+    }
+
+    #[test]
+    fn date_looking_strings_parse_back_as_strings_without_date_detection() {
+        let mut map = DataMap::new();
+        map.put_string("s", "2024-08-25T12:15:28Z");
+        let value = DataValue::DataMap(map);
+        let text = serialize(&value);
+        let options = ParseOptions { date_detection: DateDetection::Off, ..ParseOptions::default() };
+        assert_eq!(parse_with(&text, &options).unwrap(), value);
+        match parse(&text).unwrap() {
+            DataValue::DataMap(map) => assert!(matches!(map.get("s"), DataValue::Date(_))),
+            other => panic!("{:?}", other)
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use super::super::{datalist::DataList, datamap::DataMap, DataError, DataErrorKind, DataPath, DataValue, Number, Position};
+use super::{write_string, Output, SerializeOptions};
+
+/// Largest integer magnitude that an IEEE 754 double holds exactly.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+pub fn write_value(buffer: &mut String, val: &DataValue, path: &mut DataPath) -> Result<(), DataError> {
+    match val {
+        DataValue::String(s) => write_str(buffer, s),
+        DataValue::Number(n) => write_number(buffer, n, path)?,
+        DataValue::Bool(b) => buffer.push_str(if *b { "true" } else { "false" }),
+        // Not the text the date was parsed from, hence `DateDetection::Off` for canonical input.
+        DataValue::Date(d) => write_str(buffer, &d.to_string()),
+        DataValue::DataMap(map) => write_map(buffer, map, path)?,
+        DataValue::DataList(list) => write_list(buffer, list, path)?,
+        DataValue::Null | DataValue::None => buffer.push_str("null")
+    }
+    return Ok(());
+}
+
+/// Writes the entries in the order of their keys' UTF-16 code units, as RFC 8785 requires, which
+/// differs from `str` order for characters beyond the Basic Multilingual Plane.
+pub fn write_map(buffer: &mut String, map: &DataMap, path: &mut DataPath) -> Result<(), DataError> {
+    let mut entries: Vec<(&String, &DataValue)> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+    buffer.push('{');
+    for (i, (key, val)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            buffer.push(',');
+        }
+        write_str(buffer, key);
+        buffer.push(':');
+        path.push_key(key);
+        write_value(buffer, val, path)?;
+        path.segments.pop();
+    }
+    buffer.push('}');
+    return Ok(());
+}
+
+pub fn write_list(buffer: &mut String, list: &DataList, path: &mut DataPath) -> Result<(), DataError> {
+    buffer.push('[');
+    for (i, val) in list.iter().enumerate() {
+        if i > 0 {
+            buffer.push(',');
+        }
+        path.push_index(i);
+        write_value(buffer, val, path)?;
+        path.segments.pop();
+    }
+    buffer.push(']');
+    return Ok(());
+}
+
+/// Only quotes, backslashes and control characters are escaped, the latter as `\b`, `\t`, `\n`,
+/// `\f`, `\r` or lower case `\u00xx`, which is exactly what `write_string` does without `ascii_only`.
+fn write_str(buffer: &mut String, s: &str) {
+    // Writing to a `String` cannot fail.
+    let _ = write_string(&mut Output::new(buffer), s, &SerializeOptions::compact());
+}
+
+/// Numbers are written as ECMAScript writes the double they convert to, so integers beyond 2^53
+/// are rounded as a JavaScript consumer would read them.
+fn write_number(buffer: &mut String, n: &Number, path: &DataPath) -> Result<(), DataError> {
+    match n {
+        Number::Int(i) if i.unsigned_abs() <= MAX_SAFE_INTEGER => buffer.push_str(&i.to_string()),
+        Number::UInt(u) if *u <= MAX_SAFE_INTEGER => buffer.push_str(&u.to_string()),
+        Number::Int(i) => write_double(buffer, *i as f64),
+        Number::UInt(u) => write_double(buffer, *u as f64),
+        Number::Float(f) if f.is_finite() => write_double(buffer, *f),
+        _ => {
+            let error = DataError::new(DataErrorKind::NonFiniteNumber, "NaN and infinite numbers have no canonical form", Position::start());
+            return Err(error.with_path(path.clone()));
+        }
+    }
+    return Ok(());
+}
+
+/// ECMAScript `Number.prototype.toString` for a finite double: the shortest digits that read back
+/// as the same double, positioned as plain decimals from 1e-7 up to 1e21 and in exponent form
+/// outside that range.
+fn write_double(buffer: &mut String, f: f64) {
+    if f == 0.0 {
+        buffer.push('0');
+        return;
+    }
+    if f < 0.0 {
+        buffer.push('-');
+    }
+    let (digits, exp) = shortest_digits(f.abs());
+    let k = digits.len() as i32;
+    let n = exp + 1;
+    if k <= n && n <= 21 {
+        buffer.push_str(&digits);
+        buffer.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        buffer.push_str(&digits[..n as usize]);
+        buffer.push('.');
+        buffer.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        buffer.push_str("0.");
+        buffer.extend(std::iter::repeat_n('0', (-n) as usize));
+        buffer.push_str(&digits);
+    } else {
+        buffer.push_str(&digits[..1]);
+        if k > 1 {
+            buffer.push('.');
+            buffer.push_str(&digits[1..]);
+        }
+        buffer.push('e');
+        buffer.push(if n > 0 { '+' } else { '-' });
+        buffer.push_str(&(n - 1).abs().to_string());
+    }
+}
+
+/// Shortest digits that read back as `f`, with the exponent of the first digit. `{:e}` finds them,
+/// but where two candidates are equally near the exact value it takes the upper one, whereas
+/// ECMAScript takes the even one.
+fn shortest_digits(f: f64) -> (String, i32) {
+    let (digits, exp) = split_exp(&format!("{:e}", f));
+    let k = digits.len();
+    // A tie needs the exact value to end in a 5 just past the last digit, so one more digit rounded
+    // is a cheap filter before expanding the value exactly.
+    if !split_exp(&format!("{:.*e}", k, f)).0.ends_with('5') {
+        return (digits, exp);
+    }
+    let (exact, exact_exp) = split_exp(&format!("{:.800e}", f));
+    if exact_exp != exp || &exact[k..k + 1] != "5" || exact[k + 1..].bytes().any(|b| b != b'0') {
+        return (digits, exp);
+    }
+    let lower: u64 = exact[..k].parse().unwrap_or(0);
+    for candidate in [lower, lower + 1] {
+        let s = candidate.to_string();
+        if candidate % 2 == 0 && s.len() == k && format!("{}.{}e{}", &s[..1], &s[1..], exp).parse() == Ok(f) {
+            return (s.trim_end_matches('0').to_string(), exp);
+        }
+    }
+    return (digits, exp);
+}
+
+/// Digits and exponent of `d.ddde±x`.
+fn split_exp(sci: &str) -> (String, i32) {
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((sci, "0"));
+    return (mantissa.chars().filter(|c| *c != '.').collect(), exp.parse().unwrap_or(0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::parsers::{parse, parse_with, DateDetection, ParseOptions};
+    use super::super::super::{DataErrorKind, DataValue, Number};
+    use super::super::{fingerprint, serialize_canonical};
+
+    /// The IEEE 754 test vectors of RFC 8785, Appendix B.
+    const NUMBERS: &[(u64, &str)] = &[
+        (0x0000000000000000, "0"),
+        (0x8000000000000000, "0"),
+        (0x0000000000000001, "5e-324"),
+        (0x8000000000000001, "-5e-324"),
+        (0x7fefffffffffffff, "1.7976931348623157e+308"),
+        (0xffefffffffffffff, "-1.7976931348623157e+308"),
+        (0x4340000000000000, "9007199254740992"),
+        (0xc340000000000000, "-9007199254740992"),
+        (0x4430000000000000, "295147905179352830000"),
+        (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+        (0x44b52d02c7e14af6, "1e+23"),
+        (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+        (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+        (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+        (0x444b1ae4d6e2ef50, "1e+21"),
+        (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+        (0x3eb0c6f7a0b5ed8d, "0.000001"),
+        (0x41b3de4355555553, "333333333.3333332"),
+        (0x41b3de4355555554, "333333333.33333325"),
+        (0x41b3de4355555555, "333333333.3333333"),
+        (0x41b3de4355555556, "333333333.3333334"),
+        (0x41b3de4355555557, "333333333.33333343"),
+        (0xbecbf647612f3696, "-0.0000033333333333333333"),
+        (0x43143ff3c1cb0959, "1424953923781206.2")
+    ];
+
+    #[test]
+    fn rfc8785_numbers() {
+        for (bits, expected) in NUMBERS {
+            let value = DataValue::Number(Number::Float(f64::from_bits(*bits)));
+            assert_eq!(serialize_canonical(&value).unwrap(), *expected, "{:016x}", bits);
+        }
+        assert_eq!(serialize_canonical(&DataValue::Number(Number::Int(-(1 << 53)))).unwrap(), "-9007199254740992");
+        assert_eq!(serialize_canonical(&DataValue::Number(Number::UInt(u64::MAX))).unwrap(), "18446744073709552000");
+        for n in [Number::NaN, Number::PositiveInfinity, Number::NegativeInfinity, Number::Float(f64::NAN)] {
+            assert_eq!(serialize_canonical(&DataValue::Number(n)).err().map(|e| e.kind), Some(DataErrorKind::NonFiniteNumber));
+        }
+        let error = serialize_canonical(&parse("[1, {\"a\": NaN}]").unwrap()).err().map(|e| e.path.to_string());
+        assert_eq!(error.as_deref(), Some("[1].a"));
+    }
+
+    #[test]
+    fn rfc8785_sample() {
+        let input = concat!(
+            "{\"numbers\": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001], ",
+            "\"string\": \"\\u20ac$\\u000F\\u000aA'\\u0042\\u0022\\u005c\\\\\\\"\\/\", \"literals\": [null, true, false]}"
+        );
+        let expected = concat!(
+            "{\"literals\":[null,true,false],\"numbers\":[333333333.3333333,1e+30,4.5,0.002,1e-27],",
+            "\"string\":\"€$\\u000f\\nA'B\\\"\\\\\\\\\\\"/\"}"
+        );
+        assert_eq!(serialize_canonical(&parse(input).unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn rfc8785_key_order() {
+        let input = concat!(
+            "{\"\\u20ac\": \"Euro Sign\", \"\\r\": \"Carriage Return\", \"\\ufb33\": \"Hebrew Letter Dalet With Dagesh\", \"1\": \"One\", ",
+            "\"\\ud83d\\ude00\": \"Emoji: Grinning Face\", \"\\u0080\": \"Control\", \"\\u00f6\": \"Latin Small Letter O With Diaeresis\"}"
+        );
+        let expected = concat!(
+            "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\"ö\":\"Latin Small Letter O With Diaeresis\",",
+            "\"€\":\"Euro Sign\",\"😀\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+        );
+        assert_eq!(serialize_canonical(&parse(input).unwrap()).unwrap(), expected);
+    }
+
+    #[test]
+    fn fingerprint_ignores_key_order_and_formatting() {
+        let a = fingerprint(&parse("{\"a\": 1, \"b\": [1.0, \"x\"]}").unwrap()).unwrap();
+        let b = fingerprint(&parse("{ \"b\" : [ 1 , \"\\u0078\" ] , \"a\" : 1e0 }").unwrap()).unwrap();
+        let c = fingerprint(&parse("{\"a\": 1, \"b\": [\"x\", 1]}").unwrap()).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn date_looking_strings_keep_their_text() {
+        let options = ParseOptions { date_detection: DateDetection::Off, ..ParseOptions::default() };
+        let input = concat!(
+            "{\"a\":\"2024-08-25T12:15:28.000Z\",\"b\":\"2024-08-25T12:15:28+00:00\",\"c\":\"2024-08-25\",",
+            "\"d\":\"2024-08-25T12:15:28Z\"}"
+        );
+        assert_eq!(serialize_canonical(&parse_with(input, &options).unwrap()).unwrap(), input);
+        let a = fingerprint(&parse_with("\"2024-08-25T12:15:28.000Z\"", &options).unwrap()).unwrap();
+        let b = fingerprint(&parse_with("\"2024-08-25T12:15:28Z\"", &options).unwrap()).unwrap();
+        assert_ne!(a, b);
+
+        // Parsed as dates, different texts of the same instant are rewritten alike.
+        assert_eq!(serialize_canonical(&parse("\"2024-08-25T12:15:28.000Z\"").unwrap()).unwrap(), "\"2024-08-25T12:15:28Z\"");
+    }
+}
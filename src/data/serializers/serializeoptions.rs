@@ -0,0 +1,85 @@
+/// Line ending written between lines of pretty output.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n"
+        }
+    }
+}
+
+/// Controls the text produced by the serializers. The default is the pretty output the serializer
+/// has always written, `compact()` leaves out all whitespace for sending over the wire.
+#[derive(Clone)]
+pub struct SerializeOptions {
+    /// Entries on lines of their own, indented by depth. Otherwise everything is written on one line
+    /// without whitespace.
+    pub pretty: bool,
+    /// Written once per level of nesting in front of each line of pretty output.
+    pub indent: String,
+    pub line_ending: LineEnding,
+    /// A space between a key's `:` and its value.
+    pub space_after_colon: bool,
+    /// In pretty output, a map or list is written on one line when that line stays within this
+    /// many columns, and is otherwise broken over several lines, with its entries given the same
+    /// choice. `None` always breaks.
+    pub max_line_width: Option<usize>,
+    /// Writes map entries in key order rather than the map's own order, making the output the same
+    /// from run to run.
+    pub sort_keys: bool,
+    /// Escape every character outside ASCII as `\uXXXX`, using a surrogate pair beyond the Basic
+    /// Multilingual Plane, for consumers that cannot handle UTF-8.
+    pub ascii_only: bool
+}
+
+#[allow(dead_code)]
+impl SerializeOptions {
+    pub fn pretty() -> Self {
+        Self {
+            pretty: true,
+            indent: String::from("  "),
+            line_ending: LineEnding::CrLf,
+            space_after_colon: false,
+            max_line_width: None,
+            sort_keys: false,
+            ascii_only: false
+        }
+    }
+
+    pub fn compact() -> Self {
+        Self {
+            pretty: false,
+            indent: String::new(),
+            ..Self::pretty()
+        }
+    }
+
+    /// Pretty output for people, logs and golden files: short maps and lists on one line within
+    /// 80 columns, keys sorted and `\n` line endings.
+    pub fn readable() -> Self {
+        Self {
+            line_ending: LineEnding::Lf,
+            space_after_colon: true,
+            max_line_width: Some(80),
+            sort_keys: true,
+            ..Self::pretty()
+        }
+    }
+
+    pub fn ascii() -> Self {
+        Self { ascii_only: true, ..Self::default() }
+    }
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self::pretty()
+    }
+}
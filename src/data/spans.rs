@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{DataPath, Position};
+
+/// Range of the input a value or key was read from, `end` being just past its last character.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} to {}", self.start, self.end)
+    }
+}
+
+/// Where each value of a parsed document, and the key of each map entry, came from in the input,
+/// looked up by path. Produced by `parsers::parse_with_spans`. When a key occurs more than once in
+/// a map, the spans are those of its last occurrence.
+#[derive(Default)]
+pub struct Spans {
+    values: HashMap<DataPath, Span>,
+    keys: HashMap<DataPath, Span>
+}
+
+#[allow(dead_code)]
+impl Spans {
+    pub fn new() -> Self {
+        Self { values: HashMap::new(), keys: HashMap::new() }
+    }
+
+    /// Span of the value at `path`, e.g. `DataPath::from("server.timeout")`. The empty path is the
+    /// whole document.
+    pub fn value(&self, path: &DataPath) -> Option<Span> {
+        self.values.get(path).copied()
+    }
+
+    /// Span of the key of the map entry at `path`, including any quotes.
+    pub fn key(&self, path: &DataPath) -> Option<Span> {
+        self.keys.get(path).copied()
+    }
+
+    pub fn insert_value(&mut self, path: DataPath, span: Span) {
+        self.values.insert(path, span);
+    }
+
+    pub fn insert_key(&mut self, path: DataPath, span: Span) {
+        self.keys.insert(path, span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parsers::{parse_with_spans, ParseOptions};
+    use super::super::{DataPath, Position};
+    use super::{Span, Spans};
+
+    const INPUT: &str = "{\"name\": \"caf\u{e9}\",\n \"list\": [1, {\"a\": true, \"b\": [2]}],\n \"n\": -1.5e3}";
+
+    fn spans() -> Spans {
+        return parse_with_spans(INPUT, &ParseOptions::default()).unwrap().1;
+    }
+
+    fn text(span: Option<Span>) -> &'static str {
+        let span = span.unwrap();
+        return &INPUT[span.start.offset..span.end.offset];
+    }
+
+    #[test]
+    fn spans_of_keys_include_their_quotes() {
+        let spans = spans();
+        assert_eq!(text(spans.key(&DataPath::from("name"))), "\"name\"");
+        assert_eq!(text(spans.key(&DataPath::from("list[1].b"))), "\"b\"");
+        assert_eq!(spans.key(&DataPath::from("list")).unwrap().start, Position { offset: 19, line: 2, column: 2 });
+        assert_eq!(spans.key(&DataPath::from("list[0]")), None);
+        assert_eq!(spans.key(&DataPath::new()), None);
+    }
+
+    #[test]
+    fn spans_of_scalars() {
+        let spans = spans();
+        assert_eq!(text(spans.value(&DataPath::from("name"))), "\"caf\u{e9}\"");
+        assert_eq!(text(spans.value(&DataPath::from("list[0]"))), "1");
+        assert_eq!(text(spans.value(&DataPath::from("list[1].a"))), "true");
+        assert_eq!(text(spans.value(&DataPath::from("list[1].b[0]"))), "2");
+        let n = spans.value(&DataPath::from("n")).unwrap();
+        assert_eq!(&INPUT[n.start.offset..n.end.offset], "-1.5e3");
+        assert_eq!((n.start.line, n.start.column, n.end.column), (3, 7, 13));
+        // Columns count characters and offsets count bytes, so the two byte "é" makes them differ.
+        let name = spans.value(&DataPath::from("name")).unwrap();
+        assert_eq!((name.start.column, name.end.column, name.end.offset - name.start.offset), (10, 16, 7));
+        assert_eq!(spans.value(&DataPath::from("missing")), None);
+    }
+
+    #[test]
+    fn spans_of_containers_run_from_open_to_close() {
+        let spans = spans();
+        assert_eq!(text(spans.value(&DataPath::from("list[1]"))), "{\"a\": true, \"b\": [2]}");
+        assert_eq!(text(spans.value(&DataPath::from("list[1].b"))), "[2]");
+        assert_eq!(text(spans.value(&DataPath::from("list"))), "[1, {\"a\": true, \"b\": [2]}]");
+        let root = spans.value(&DataPath::new()).unwrap();
+        assert_eq!((root.start, root.end.offset), (Position::start(), INPUT.len()));
+        assert_eq!((root.end.line, root.end.column), (3, 14));
+    }
+
+    #[test]
+    fn repeated_keys_keep_the_last_spans() {
+        let input = "{\"a\": 1, \"a\": [22]}";
+        let (_, spans) = parse_with_spans(input, &ParseOptions::default()).unwrap();
+        assert_eq!(spans.key(&DataPath::from("a")).unwrap().start.offset, 9);
+        let value = spans.value(&DataPath::from("a")).unwrap();
+        assert_eq!(&input[value.start.offset..value.end.offset], "[22]");
+    }
+}
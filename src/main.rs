@@ -1,7 +1,7 @@
-mod data;
+use std::io::IsTerminal;
 
 use chrono::Utc;
-use data::parsers;
+use firsttest::data::{parsers, RenderStyle};
 
 fn main() {
     let data_str = "{ \"dt\":\"2024-08-25T12:15:28.999+10:00\", num: 8.288 }";
@@ -10,6 +10,9 @@ fn main() {
             map.put_date_utc("date", Utc::now());
             println!("Deserialized is: {}", map);
         },
-        Err(e) => print!("Error: {}", e.message),
+        Err(e) => {
+            let style = if std::io::stderr().is_terminal() { RenderStyle::Ansi } else { RenderStyle::Plain };
+            eprint!("{}", e.render(data_str, style));
+        },
     }    
 }